@@ -9,6 +9,7 @@ pub type ColorPieceList = [PieceList; 2];
 
 #[derive(Clone)]
 struct History {
+    m: Move,
     from: Bitboard,
     to: Bitboard,
     castle_kingside: ColorBool,
@@ -36,6 +37,7 @@ impl History {
         hash: u64,
     ) -> Self {
         Self {
+            m: Move::new(),
             from,
             to,
             castle_kingside,
@@ -267,6 +269,21 @@ impl Board {
         self.hash
     }
 
+    pub fn moves_played(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn move_history(&self) -> String {
+        self.history
+            .iter()
+            .map(|entry| match entry.from.not_empty() {
+                true => entry.m.to_uci(),
+                false => String::from("0000"), // null move
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
     fn check_side(&self, mask: Bitboard) -> Side {
         if (self.occupied[Side::White] & mask).not_empty() {
             return Side::White;
@@ -276,10 +293,7 @@ impl Board {
             return Side::Black;
         }
 
-        eprintln!("Board history:");
-        for entry in &self.history {
-            eprintln!("* {}{}", entry.from.peek(), entry.to.peek());
-        }
+        eprintln!("Board history: {}", self.move_history());
         panic!("Internal error: there should be something on {} ({:#066b})", mask.peek(), mask);
     }
 
@@ -352,6 +366,7 @@ impl Board {
             self.checkmate,
             self.hash,
         );
+        history_entry.m = m;
 
         if self.has_piece(to_mask) {
             history_entry.capture = self.check_piece(opponent, to_mask);
@@ -514,7 +529,7 @@ mod tests {
 
     impl Board {
         fn make_move_str(&mut self, from: &str, to: &str) {
-            self.make_move(Move::from_str(from, to));
+            self.make_move(Move::from_str(from, to).unwrap());
         }
 
         fn assert_position(&self, fen: &str) {
@@ -530,7 +545,7 @@ mod tests {
 
     #[test]
     fn test_capture_moves() {
-        let mut board = Board::from_fen("r2qkbnr/ppp1pppp/2n5/3p1b2/1P2P3/2N5/P1PP1PPP/R1BQKBNR w KQkq - 1 4");
+        let mut board = Board::from_fen("r2qkbnr/ppp1pppp/2n5/3p1b2/1P2P3/2N5/P1PP1PPP/R1BQKBNR w KQkq - 1 4").unwrap();
         board.make_move_str("e4", "f5");
         board.assert_position("r2qkbnr/ppp1pppp/2n5/3p1P2/1P6/2N5/P1PP1PPP/R1BQKBNR b KQkq - 0 4");
         board.make_move_str("c6", "b4");
@@ -551,7 +566,7 @@ mod tests {
 
     #[test]
     fn test_castle_moves() {
-        let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
         board.make_move_str("e1", "g1");
         board.assert_position("r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
         board.make_move_str("h8", "h2");
@@ -572,11 +587,12 @@ mod tests {
 
     #[test]
     fn test_promotion() {
-        let mut board = Board::from_fen("rnbqkbnr/p1pppppP/8/8/8/8/PPpPPP1P/RNBQKBNR w KQkq - 0 5");
-        board.make_move(Move::from_str_prom("h7", "g8", Promotion::Queen));
+        let mut board = Board::from_fen("rnbqkbnr/p1pppppP/8/8/8/8/PPpPPP1P/RNBQKBNR w KQkq - 0 5").unwrap();
+        board.make_move(Move::from_str_prom("h7", "g8", Promotion::Queen).unwrap());
         board.assert_position("rnbqkbQr/p1ppppp1/8/8/8/8/PPpPPP1P/RNBQKBNR b KQkq - 0 5");
-        board.make_move(Move::from_str_prom("c2", "d1", Promotion::Knight));
+        board.make_move(Move::from_str_prom("c2", "d1", Promotion::Knight).unwrap());
         board.assert_position("rnbqkbQr/p1ppppp1/8/8/8/8/PP1PPP1P/RNBnKBNR w KQkq - 0 6");
+        assert_eq!(board.move_history(), "h7g8q c2d1n");
         board.unmake_move();
         board.assert_position("rnbqkbQr/p1ppppp1/8/8/8/8/PPpPPP1P/RNBQKBNR b KQkq - 0 5");
         board.unmake_move();
@@ -585,7 +601,7 @@ mod tests {
 
     #[test]
     fn test_en_passant() {
-        let mut board = Board::from_fen("rnbqkbnr/pppp1pp1/7p/3Pp3/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 3");
+        let mut board = Board::from_fen("rnbqkbnr/pppp1pp1/7p/3Pp3/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 3").unwrap();
         board.make_move_str("d5", "e6");
         board.assert_position("rnbqkbnr/pppp1pp1/4P2p/8/8/8/PPP1PPPP/RNBQKBNR b KQkq - 0 3");
         board.make_move_str("d7", "d5");
@@ -617,7 +633,7 @@ mod tests {
 
         impl Board {
             fn uci(&mut self, m: &str) {
-                self.make_move(Move::from_uci(m));
+                self.make_move(Move::from_uci(m).unwrap());
             }
         }
 
@@ -638,7 +654,7 @@ mod tests {
         #[test]
         fn bug_2() {
             // kiwipete e1d1 e8c8 d1c1 (undo) a1b1 crash
-            let mut board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+            let mut board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
             board.uci("e1d1");
             board.assert_position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R2K3R b kq - 1 1");
             board.uci("e8c8");
@@ -654,7 +670,7 @@ mod tests {
         #[test]
         fn bug_3() {
             // kiwipete e2d1 a6f1 (undo) crash
-            let mut board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+            let mut board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
             board.uci("e2d1");
             board.assert_position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPB1PPP/R2BK2R b KQkq - 1 1");
             board.uci("a6f1");
@@ -666,7 +682,7 @@ mod tests {
         #[test]
         fn bug_4() {
             // r1bqkbnr/pppp2pp/8/4pp2/8/2NnP1PP/PPPPNP2/R1BQKB1R w KQkq - 4 3 should detect in check
-            let mut board = Board::from_fen("r1bqkbnr/pppp2pp/8/4pp2/8/2NnP1PP/PPPPNP2/R1BQKB1R w KQkq - 4 3");
+            let mut board = Board::from_fen("r1bqkbnr/pppp2pp/8/4pp2/8/2NnP1PP/PPPPNP2/R1BQKB1R w KQkq - 4 3").unwrap();
             assert_eq!(board.in_check(), true);
         }
     }
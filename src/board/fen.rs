@@ -2,7 +2,7 @@ use crate::board::Board;
 use crate::types::{Bitboard, Side, Square};
 
 pub trait FenConsumer {
-    fn from_fen(fen: &str) -> Self;
+    fn from_fen(fen: &str) -> Result<Self, String> where Self: Sized;
 }
 
 pub trait FenProducer {
@@ -10,49 +10,61 @@ pub trait FenProducer {
 }
 
 impl FenConsumer for Board {
-    fn from_fen(fen: &str) -> Self {
+    fn from_fen(fen: &str) -> Result<Self, String> {
         let mut board = Self::new();
         let mut fen = fen.chars();
 
         for rank in 0..8 {
             let mut file = 0;
             loop {
-                match fen.next() {
-                    None => return board,
+                let piece = match fen.next() {
+                    None => return Err(String::from("Invalid fen, unexpected end of piece placement")),
                     Some('/') => break,
                     Some(' ') => break,
-                    Some('2') => file += 1,
-                    Some('3') => file += 2,
-                    Some('4') => file += 3,
-                    Some('5') => file += 4,
-                    Some('6') => file += 5,
-                    Some('7') => file += 6,
-                    Some('8') => file += 7,
-                    Some('k') => board.put_king(Side::Black, Bitboard::from_coords(file, 7 - rank)),
-                    Some('K') => board.put_king(Side::White, Bitboard::from_coords(file, 7 - rank)),
-                    Some('q') => board.put_queen(Side::Black, Bitboard::from_coords(file, 7 - rank)),
-                    Some('Q') => board.put_queen(Side::White, Bitboard::from_coords(file, 7 - rank)),
-                    Some('r') => board.put_rook(Side::Black, Bitboard::from_coords(file, 7 - rank)),
-                    Some('R') => board.put_rook(Side::White, Bitboard::from_coords(file, 7 - rank)),
-                    Some('p') => board.put_pawn(Side::Black, Bitboard::from_coords(file, 7 - rank)),
-                    Some('P') => board.put_pawn(Side::White, Bitboard::from_coords(file, 7 - rank)),
-                    Some('n') => board.put_knight(Side::Black, Bitboard::from_coords(file, 7 - rank)),
-                    Some('N') => board.put_knight(Side::White, Bitboard::from_coords(file, 7 - rank)),
-                    Some('b') => board.put_bishop(Side::Black, Bitboard::from_coords(file, 7 - rank)),
-                    Some('B') => board.put_bishop(Side::White, Bitboard::from_coords(file, 7 - rank)),
-                    _ => {}
+                    Some(x) if ('1'..='8').contains(&x) => {
+                        file += x.to_digit(10).unwrap() as usize;
+                        continue;
+                    }
+                    Some(x) => x,
+                };
+
+                if file >= 8 {
+                    return Err(format!("Invalid fen, too many squares on rank {}", 8 - rank));
+                }
+
+                let mask = Bitboard::from_coords(file, 7 - rank);
+                match piece {
+                    'k' => board.put_king(Side::Black, mask),
+                    'K' => board.put_king(Side::White, mask),
+                    'q' => board.put_queen(Side::Black, mask),
+                    'Q' => board.put_queen(Side::White, mask),
+                    'r' => board.put_rook(Side::Black, mask),
+                    'R' => board.put_rook(Side::White, mask),
+                    'p' => board.put_pawn(Side::Black, mask),
+                    'P' => board.put_pawn(Side::White, mask),
+                    'n' => board.put_knight(Side::Black, mask),
+                    'N' => board.put_knight(Side::White, mask),
+                    'b' => board.put_bishop(Side::Black, mask),
+                    'B' => board.put_bishop(Side::White, mask),
+                    x => return Err(format!("Invalid fen, unknown piece: {}", x)),
                 }
                 file += 1;
             }
         }
 
+        if board.kings[Side::White].pieces() != 1 || board.kings[Side::Black].pieces() != 1 {
+            return Err(String::from("Invalid fen, each side needs exactly one king"));
+        }
+
         match fen.next() {
             Some('w') => board.current_color = Side::White,
             Some('b') => board.current_color = Side::Black,
-            _ => panic!("Invalid fen, expected color to play"),
+            _ => return Err(String::from("Invalid fen, expected color to play")),
         }
 
-        assert_eq!(fen.next(), Some(' '), "Invalid fen, expected space");
+        if fen.next() != Some(' ') {
+            return Err(String::from("Invalid fen, expected space"));
+        }
 
         board.castle_kingside = [false, false];
         board.castle_queenside = [false, false];
@@ -68,7 +80,7 @@ impl FenConsumer for Board {
                 Some('k') => board.castle_kingside[Side::Black] = true,
                 Some('Q') => board.castle_queenside[Side::White] = true,
                 Some('q') => board.castle_queenside[Side::Black] = true,
-                _ => panic!("Invalid fen, expected castling rights"),
+                _ => return Err(String::from("Invalid fen, expected castling rights")),
             }
         }
 
@@ -80,11 +92,10 @@ impl FenConsumer for Board {
                 }
                 Some(' ') => break,
                 Some(file) if file.is_alphabetic() => {
-                    let rank = fen.next().unwrap();
-                    // TODO: fix this monstrosity
-                    board.en_passant = Bitboard::from(Square::from(format!("{}{}", file, rank).as_str()))
+                    let rank = fen.next().ok_or("Invalid fen, expected en passant rank")?;
+                    board.en_passant = Bitboard::from(Square::try_from(format!("{}{}", file, rank).as_str())?)
                 }
-                _ => panic!("Invalid fen, expected en passant data"),
+                _ => return Err(String::from("Invalid fen, expected en passant data")),
             }
         }
 
@@ -93,10 +104,10 @@ impl FenConsumer for Board {
         loop {
             match fen.next() {
                 Some(' ') => break,
-                Some(x) if x.is_digit(10) => {
+                Some(x) if x.is_ascii_digit() => {
                     board.half_moves_clock = board.half_moves_clock * 10 + x.to_digit(10).unwrap()
                 }
-                _ => panic!("Invalid fen, expected half move count"),
+                _ => return Err(String::from("Invalid fen, expected half move count")),
             }
         }
 
@@ -104,7 +115,7 @@ impl FenConsumer for Board {
 
         loop {
             match fen.next() {
-                Some(x) if x.is_digit(10) => {
+                Some(x) if x.is_ascii_digit() => {
                     board.full_moves_count = board.full_moves_count * 10 + x.to_digit(10).unwrap()
                 }
                 _ => break,
@@ -112,7 +123,7 @@ impl FenConsumer for Board {
         }
 
         board.update_hash();
-        board
+        Ok(board)
     }
 }
 
@@ -215,4 +226,27 @@ impl FenProducer for Board {
 
         result
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(Board::from_fen(fen).unwrap().export_fen(), fen);
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(Board::from_fen("").is_err());
+        assert!(Board::from_fen("rnbqkbnr/pppppppp/8/8").is_err());
+        assert!(Board::from_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_err());
+        assert!(Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR w KQkq - 0 1").is_err());
+        assert!(Board::from_fen("rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1").is_err());
+        assert!(Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1").is_err());
+        assert!(Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1").is_err());
+        assert!(Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq z9 0 1").is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Prints a line to the GUI, recording it in the debug log (if there is one)
+#[macro_export]
+macro_rules! output {
+    ($($arg:tt)*) => {
+        $crate::console::write(format!($($arg)*))
+    };
+}
+
+static LOG: Mutex<Option<File>> = Mutex::new(None);
+
+pub fn set_log_file(path: &str) -> Result<(), String> {
+    let mut log = LOG.lock().unwrap();
+    *log = match path {
+        "" | "<empty>" => None,
+        path => Some(OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("cannot open {}: {}", path, e))?),
+    };
+    Ok(())
+}
+
+fn log(direction: &str, line: &str) {
+    if let Some(file) = LOG.lock().unwrap().as_mut() {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        // a failing log should never break the communication with the GUI
+        let _ = writeln!(file, "[{}.{:03}] {} {}", timestamp.as_secs(), timestamp.subsec_millis(), direction, line);
+    }
+}
+
+pub fn log_input(line: &str) {
+    log("<<", line);
+}
+
+pub fn write(line: String) {
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{}", line).expect("Writing to stdout failed");
+    stdout.flush().expect("Writing to stdout failed");
+    log(">>", line.as_str());
+}
@@ -0,0 +1,82 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::panic;
+use std::sync::Mutex;
+use crate::board::{Board, FenProducer};
+
+struct Context {
+    position: String,
+    fen: String,
+    history: String,
+    go: String,
+    crash_file: Option<String>,
+}
+
+static CONTEXT: Mutex<Context> = Mutex::new(Context {
+    position: String::new(),
+    fen: String::new(),
+    history: String::new(),
+    go: String::new(),
+    crash_file: None,
+});
+
+/// Remembers the command which has set up the current position, to be included in crash reports
+pub fn set_position(command: &str, board: &Board) {
+    let mut context = CONTEXT.lock().unwrap();
+    context.position = String::from(command);
+    context.fen = board.export_fen();
+    context.history = board.move_history();
+}
+
+pub fn set_go(command: &str) {
+    CONTEXT.lock().unwrap().go = String::from(command);
+}
+
+pub fn set_crash_file(path: &str) {
+    CONTEXT.lock().unwrap().crash_file = match path {
+        "" | "<empty>" => None,
+        path => Some(String::from(path)),
+    };
+}
+
+fn report(info: &panic::PanicHookInfo) -> (String, Option<String>) {
+    let thread = std::thread::current();
+    let mut report = format!(
+        "=== kopyto crash report ===\nthread '{}' {}\n",
+        thread.name().unwrap_or("<unnamed>"),
+        info);
+
+    // the panic might have happened while the context was being updated, in such case
+    // it is better to lose it than to deadlock
+    let crash_file = match CONTEXT.try_lock() {
+        Ok(context) => {
+            report += &format!(
+                "position command: {}\nfen: {}\nmove history: {}\ngo command: {}\n",
+                context.position, context.fen, context.history, context.go);
+            context.crash_file.clone()
+        }
+        Err(_) => {
+            report += "context unavailable\n";
+            None
+        }
+    };
+
+    (report, crash_file)
+}
+
+pub fn install() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        let (report, crash_file) = report(info);
+        eprint!("{}", report);
+
+        if let Some(path) = crash_file {
+            let file = OpenOptions::new().create(true).append(true).open(&path);
+            if let Err(e) = file.and_then(|mut file| file.write_all(report.as_bytes())) {
+                eprintln!("cannot write crash report to {}: {}", path, e);
+            }
+        }
+    }));
+}
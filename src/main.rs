@@ -1,12 +1,139 @@
+#[macro_use]
+mod console;
 mod board;
+mod crash;
 mod search;
 mod types;
 mod uci;
 mod moves_generation;
 mod transpositions;
 
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
 use board::masks;
+use board::{Board, FenConsumer};
+use search::{Searcher, Signals, Verbosity, SEARCH_STACK_SIZE};
+use transpositions::Transpositions;
 
-fn main() {
-    uci::start();
+const USAGE: &str = "usage:
+    kopyto                              start in UCI mode
+    kopyto perft <fen> <depth>          count the leaf nodes up to the given depth
+    kopyto eval <fen>                   print the static evaluation of the position
+    kopyto search <fen> --depth <n>     search the position to the given depth
+    kopyto bench [depth]                run the benchmark
+    kopyto fitwdl <file>                fit the WDL model to positions with known results";
+
+fn parse<T: FromStr>(name: &str, value: Option<&String>) -> Result<T, String> {
+    match value {
+        None => Err(format!("missing {}", name)),
+        Some(value) => value.parse::<T>().map_err(|_| format!("invalid {}: '{}'", name, value)),
+    }
+}
+
+// the FEN may be given either as a single quoted argument or as several separate words
+fn parse_board(words: &[String]) -> Result<Board, String> {
+    match words.is_empty() {
+        true => Err(String::from("missing fen")),
+        false => Board::from_fen(words.join(" ").as_str()),
+    }
+}
+
+fn perft(args: &[String]) -> Result<(), String> {
+    match args.split_last() {
+        None => Err(String::from("missing fen")),
+        Some((depth, fen)) => {
+            let depth = parse("depth", Some(depth))?;
+            moves_generation::perft(&mut parse_board(fen)?, depth);
+            Ok(())
+        }
+    }
+}
+
+fn eval(args: &[String]) -> Result<(), String> {
+    println!("{}", search::evaluate(&parse_board(args)?, Verbosity::Verbose));
+    Ok(())
+}
+
+fn search(args: &[String]) -> Result<(), String> {
+    let (fen, depth) = match args.iter().position(|arg| arg == "--depth") {
+        None => return Err(String::from("missing --depth")),
+        Some(idx) => (&args[..idx], parse::<i16>("depth", args.get(idx + 1))?),
+    };
+    let board = parse_board(fen)?;
+
+    let mut options = search::Options::new();
+    options.depth = Some(depth);
+    options.infinite = true;
+
+    let result = thread::Builder::new()
+        .name(String::from("search"))
+        .stack_size(SEARCH_STACK_SIZE)
+        .spawn(move || {
+            let transpositions = Transpositions::new(64);
+            Searcher::new(board, &transpositions, false, Arc::new(Signals::new())).go(options)
+        })
+        .expect("Cannot spawn search thread")
+        .join()
+        .expect("Search thread panicked");
+
+    match result.ponder {
+        Some(ponder) => println!("bestmove {} ponder {}", result.m.to_uci(), ponder.to_uci()),
+        None => println!("bestmove {}", result.m.to_uci()),
+    }
+    Ok(())
+}
+
+fn bench(args: &[String]) -> Result<(), String> {
+    let depth = match args.first() {
+        None => search::BENCH_DEPTH,
+        depth => parse("depth", depth)?,
+    };
+    search::bench(depth);
+    Ok(())
+}
+
+fn fit_wdl(args: &[String]) -> Result<(), String> {
+    match args.first() {
+        None => Err(String::from("missing file")),
+        Some(path) => {
+            let model = search::WdlModel::fit(path)?;
+            println!("a: {:?}", model.a);
+            println!("b: {:?}", model.b);
+            Ok(())
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    crash::install();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.split_first() {
+        None => {
+            uci::start();
+            Ok(())
+        }
+        Some((command, args)) => match command.as_str() {
+            "perft" => perft(args),
+            "eval" => eval(args),
+            "search" => search(args),
+            "bench" => bench(args),
+            "fitwdl" => fit_wdl(args),
+            "help" | "--help" | "-h" => {
+                println!("{}", USAGE);
+                Ok(())
+            }
+            _ => Err(format!("unknown command: {}", command)),
+        },
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}\n{}", e, USAGE);
+            ExitCode::FAILURE
+        }
+    }
 }
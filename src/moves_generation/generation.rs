@@ -5,15 +5,61 @@ use crate::moves_generation::attacks;
 
 pub type Moves = Vec<Move>;
 
-const CAPTURES_ONLY: bool = true;
+const TACTICAL: bool = true;
 const ALL_MOVES: bool = false;
 
 pub fn generate_all(board: &Board) -> Moves {
     generate::<ALL_MOVES>(board)
 }
 
-pub fn generate_captures(board: &Board) -> Moves {
-    generate::<CAPTURES_ONLY>(board)
+/// Captures and promotions, optionally also the quiet moves giving check
+pub fn generate_tactical(board: &Board, checks: bool) -> Moves {
+    let mut moves = generate::<TACTICAL>(board);
+
+    if checks {
+        let quiet_checks: Moves = generate::<ALL_MOVES>(board)
+            .into_iter()
+            .filter(|m| !moves.contains(m) && gives_check(board, *m))
+            .collect();
+        moves.extend(quiet_checks);
+    }
+
+    moves
+}
+
+/// Checks whether the move attacks the opponent king, either directly or by discovering
+/// an attack of a slider. Castling and en passant discoveries are not taken into account.
+pub fn gives_check(board: &Board, m: Move) -> bool {
+    let side = board.side_to_move();
+    let opponent_king = board.kings[!side];
+    let king_idx = opponent_king.peek();
+    let from = Bitboard::from(m.get_from());
+    let to = m.get_to();
+    let occupied = (board.any_piece & !from) | Bitboard::from(to);
+
+    let piece = match m.has_promotion() {
+        true => Some(Piece::from(m.get_promotion())),
+        false => board.check_piece(side, from),
+    };
+
+    let direct = match piece {
+        Some(Piece::Pawn) => attacks::pawn(side, to),
+        Some(Piece::Knight) => attacks::knight(to),
+        Some(Piece::Bishop) => attacks::bishop(to, occupied),
+        Some(Piece::Rook) => attacks::rook(to, occupied),
+        Some(Piece::Queen) => attacks::queen(to, occupied),
+        Some(Piece::King) | None => Bitboard::EMPTY,
+    };
+
+    if (direct & opponent_king).not_empty() {
+        return true;
+    }
+
+    let diagonal = (board.bishops[side] | board.queens[side]) & !from;
+    let parallel = (board.rooks[side] | board.queens[side]) & !from;
+
+    (attacks::bishop(king_idx, occupied) & diagonal).not_empty()
+        || (attacks::rook(king_idx, occupied) & parallel).not_empty()
 }
 
 fn generate<const MODE: bool>(board: &Board) -> Moves {
@@ -28,7 +74,7 @@ fn generate<const MODE: bool>(board: &Board) -> Moves {
     let diagonal_pin_mask = diagonal_pin_mask(board, side, board.kings[side].peek());
 
     let mut legal_targets = match MODE {
-        CAPTURES_ONLY => board.occupied[opponent],
+        TACTICAL => board.occupied[opponent],
         ALL_MOVES => !board.occupied[side],
     };
 
@@ -190,7 +236,7 @@ fn diagonal_pin_mask(board: &Board, side: Side, king_idx: Square) -> Bitboard {
     )
 }
 
-fn generate_pawns<const CAPTURES_ONLY: bool>(
+fn generate_pawns<const MODE: bool>(
     board: &Board, side: Side, moves: &mut Moves, parallel_pins: Bitboard, diagonal_pins: Bitboard, check_mask: Bitboard
 ) {
     let opponent = !side;
@@ -249,14 +295,12 @@ fn generate_pawns<const CAPTURES_ONLY: bool>(
             moves.push(Move::from_idx_prom(src_idx, tgt_idx, Promotion::Queen));
         }
 
-        if !CAPTURES_ONLY {
-            for tgt_idx in promotion_walk {
-                let src_idx = side.choose(tgt_idx.south(), tgt_idx.north());
-                moves.push(Move::from_idx_prom(src_idx, tgt_idx, Promotion::Knight));
-                moves.push(Move::from_idx_prom(src_idx, tgt_idx, Promotion::Bishop));
-                moves.push(Move::from_idx_prom(src_idx, tgt_idx, Promotion::Rook));
-                moves.push(Move::from_idx_prom(src_idx, tgt_idx, Promotion::Queen));
-            }
+        for tgt_idx in promotion_walk {
+            let src_idx = side.choose(tgt_idx.south(), tgt_idx.north());
+            moves.push(Move::from_idx_prom(src_idx, tgt_idx, Promotion::Knight));
+            moves.push(Move::from_idx_prom(src_idx, tgt_idx, Promotion::Bishop));
+            moves.push(Move::from_idx_prom(src_idx, tgt_idx, Promotion::Rook));
+            moves.push(Move::from_idx_prom(src_idx, tgt_idx, Promotion::Queen));
         }
     }
 
@@ -272,7 +316,7 @@ fn generate_pawns<const CAPTURES_ONLY: bool>(
         moves.push(Move::from_idx(side.choose(idx.southwest(), idx.northeast()), idx));
     }
 
-    if !CAPTURES_ONLY {
+    if MODE == ALL_MOVES {
         for idx in pawns_walk {
             moves.push(Move::from_idx(side.choose(idx.south(), idx.north()), idx));
         }
@@ -382,10 +426,10 @@ mod tests {
 
     macro_rules! a_move {
         ($from:expr,$to:expr) => {
-            Move::from_str($from, $to)
+            Move::from_str($from, $to).unwrap()
         };
         ($from:expr,$to:expr,$prom:expr) => {
-            Move::from_str_prom($from, $to, $prom)
+            Move::from_str_prom($from, $to, $prom).unwrap()
         };
     }
 
@@ -399,7 +443,7 @@ mod tests {
     fn piece_move_generation_test(fen: &str, file: usize, rank: usize, expected: Moves) {
         println!("-- Move generation test at position {}", fen);
 
-        let mut board = Board::from_fen(fen);
+        let mut board = Board::from_fen(fen).unwrap();
         let moves = generate_moves_for(&mut board, Bitboard::from_coords(file, rank));
 
         move_generation_comparison(moves, expected);
@@ -722,9 +766,9 @@ mod tests {
     fn from_position() {
         let mut board = Board::from_starting_position();
 
-        board.make_move(Move::from_uci("c2c3"));
-        board.make_move(Move::from_uci("d7d6"));
-        board.make_move(Move::from_uci("d1a4"));
+        board.make_move(Move::from_uci("c2c3").unwrap());
+        board.make_move(Move::from_uci("d7d6").unwrap());
+        board.make_move(Move::from_uci("d1a4").unwrap());
 
         move_generation_comparison(
             generate::<ALL_MOVES>(&board),
@@ -738,4 +782,25 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn tactical() {
+        // capture, quiet promotions and (optionally) quiet checks
+        let board = Board::from_fen("4k3/1P6/8/1N1p4/4P3/8/8/R1B1K3 w - - 0 1").unwrap();
+
+        move_generation_comparison(
+            generate_tactical(&board, false),
+            vec![
+                a_move!("e4", "d5"),
+                a_move!("b7", "b8", Promotion::Queen),
+                a_move!("b7", "b8", Promotion::Rook),
+                a_move!("b7", "b8", Promotion::Bishop),
+                a_move!("b7", "b8", Promotion::Knight),
+            ],
+        );
+
+        let mut with_checks = generate_tactical(&board, true);
+        with_checks.retain(|m| m.get_from() == Square::B5);
+        move_generation_comparison(with_checks, vec![a_move!("b5", "c7"), a_move!("b5", "d6")]);
+    }
 }
@@ -3,10 +3,12 @@ mod generation;
 mod list;
 mod ordering;
 mod perft;
+mod see;
 
 pub use generation::Moves;
-pub use generation::{generate_all, generate_captures, real_attack_mask};
+pub use generation::{generate_all, generate_tactical, real_attack_mask};
 pub use list::MoveList;
-pub use ordering::Weights;
+pub use ordering::{HistorySet, PieceToHistory, QuietHistory, Weights};
 pub use ordering::order;
 pub use perft::perft;
+pub use see::see;
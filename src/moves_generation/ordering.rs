@@ -1,4 +1,5 @@
 use crate::board::Board;
+use crate::moves_generation::see;
 use crate::search;
 use crate::types::{Bitboard, Move, Piece, Side};
 
@@ -6,13 +7,24 @@ type PieceValues = [Option<Piece>; 64];
 type MoveWeight = i32;
 pub type Weights = Vec<MoveWeight>;
 type KillerMoveSet = [Move; search::KILLER_MOVES_STORED];
-type HistorySet = [[u32; 64]; 64];
+pub type HistorySet = [[i32; 64]; 64];
+pub type PieceToHistory = [[i32; 64]; 12];
+
+/// Scores used for ordering the quiet moves: the butterfly table of the side to move, the reply
+/// which has refuted the previous move before, and the continuation tables of the previous two moves
+pub struct QuietHistory<'a> {
+    pub butterfly: &'a HistorySet,
+    pub counter_move: Option<Move>,
+    pub continuations: [Option<&'a PieceToHistory>; 2],
+}
 
 const HASH_MOVE_VALUE: MoveWeight = MoveWeight::MAX - 1;
 const MVV_LVA_VALUE: MoveWeight = MoveWeight::MAX - 1024;
 const KILLER_MOVE_VALUE: MoveWeight = MoveWeight::MAX - 4096;
+const COUNTER_MOVE_VALUE: MoveWeight = MoveWeight::MAX - 8192;
+const LOSING_CAPTURE_VALUE: MoveWeight = MoveWeight::MAX - 32768;
 
-pub fn order(board: &Board, moves: &Vec<Move>, hash_move: Option<Move>, killer_moves: &KillerMoveSet, history: &HistorySet) -> Weights {
+pub fn order(board: &Board, moves: &Vec<Move>, hash_move: Option<Move>, killer_moves: &KillerMoveSet, history: &QuietHistory) -> Weights {
     let side = board.side_to_move();
     let attacks = board.occupied[!side];
     let pieces = cache_piece_values(board, side, moves);
@@ -20,28 +32,49 @@ pub fn order(board: &Board, moves: &Vec<Move>, hash_move: Option<Move>, killer_m
     moves.iter().map(|m| {
         match hash_move {
             Some(hashed) if &hashed == m => HASH_MOVE_VALUE,
-            _ => mvv_lva(m, attacks, &pieces, killer_moves, history),
+            _ => mvv_lva(board, side, m, attacks, &pieces, killer_moves, history),
         }
     }).collect()
 }
 
-fn mvv_lva(m: &Move, attacks: Bitboard, pieces: &PieceValues, killer_moves: &KillerMoveSet, history: &HistorySet) -> MoveWeight {
+fn mvv_lva(board: &Board, side: Side, m: &Move, attacks: Bitboard, pieces: &PieceValues, killer_moves: &KillerMoveSet, history: &QuietHistory) -> MoveWeight {
     let target_mask = Bitboard::from(m.get_to());
     match (target_mask & attacks).not_empty() {
         false => {
             match killer_moves.contains(m) {
                 true => KILLER_MOVE_VALUE,
-                false => history[m.get_from()][m.get_to()] as MoveWeight,
+                false if history.counter_move == Some(*m) => COUNTER_MOVE_VALUE,
+                false => quiet_score(side, m, pieces, history),
             }
         },
         true => {
             let defender_value = piece_value(pieces[m.get_to() as usize]);
             let attacker_value = piece_value(pieces[m.get_from() as usize]);
+
+            // capturing a more valuable piece can never lose material, other captures
+            // go after the killer moves if they lose material
+            if attacker_value > defender_value && see(board, *m) < 0 {
+                return LOSING_CAPTURE_VALUE + defender_value * 10 - attacker_value;
+            }
+
             MVV_LVA_VALUE + defender_value * 10 - attacker_value
         }
     }
 }
 
+fn quiet_score(side: Side, m: &Move, pieces: &PieceValues, history: &QuietHistory) -> MoveWeight {
+    let mut score = history.butterfly[m.get_from()][m.get_to()];
+
+    if let Some(piece) = pieces[m.get_from() as usize] {
+        let piece_idx = search::piece_index(side, piece);
+        for continuation in history.continuations.iter().flatten() {
+            score += continuation[piece_idx][m.get_to()];
+        }
+    }
+
+    score
+}
+
 fn piece_value(p: Option<Piece>) -> MoveWeight {
     match p {
         None => 0,
@@ -11,7 +11,7 @@ pub fn perft(board: &mut Board, depth: usize) -> u64 {
     } else {
         0
     };
-    println!(
+    output!(
         "depth {} nodes {} time {} nps {}",
         depth,
         nodes,
@@ -36,7 +36,7 @@ fn perft_impl(board: &mut Board, depth: usize, init: bool) -> u64 {
         board.make_move(m.clone());
         let res = perft_impl(board, depth - 1, false);
         if init {
-            println!("{:?}: {}", m, res);
+            output!("{:?}: {}", m, res);
         }
         nodes += res;
         board.unmake_move();
@@ -51,7 +51,7 @@ mod tests {
     use crate::board::FenConsumer;
 
     fn perft_run(fen: &str, depth: usize, expected: u64) {
-        let mut board = Board::from_fen(fen);
+        let mut board = Board::from_fen(fen).unwrap();
         assert_eq!(perft(&mut board, depth), expected);
     }
 
@@ -0,0 +1,156 @@
+use crate::board::Board;
+use crate::moves_generation::attacks;
+use crate::types::{Bitboard, Move, Piece, Side, Square};
+
+pub type SeeScore = i32;
+
+const EXCHANGE_ORDER: [Piece; 6] = [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King];
+
+pub fn value(piece: Piece) -> SeeScore {
+    match piece {
+        Piece::Pawn => 100,
+        Piece::Knight => 300,
+        Piece::Bishop => 300,
+        Piece::Rook => 500,
+        Piece::Queen => 900,
+        Piece::King => 10000,
+    }
+}
+
+fn pieces(board: &Board, piece: Piece) -> Bitboard {
+    match piece {
+        Piece::Pawn => board.pawns[Side::White] | board.pawns[Side::Black],
+        Piece::Knight => board.knights[Side::White] | board.knights[Side::Black],
+        Piece::Bishop => board.bishops[Side::White] | board.bishops[Side::Black],
+        Piece::Rook => board.rooks[Side::White] | board.rooks[Side::Black],
+        Piece::Queen => board.queens[Side::White] | board.queens[Side::Black],
+        Piece::King => board.kings[Side::White] | board.kings[Side::Black],
+    }
+}
+
+/// All the pieces (of both sides) attacking the square, with sliders looking through the pieces
+/// which are no longer present in the occupancy mask
+fn attackers(board: &Board, square: Square, occupied: Bitboard) -> Bitboard {
+    let diagonal = pieces(board, Piece::Bishop) | pieces(board, Piece::Queen);
+    let parallel = pieces(board, Piece::Rook) | pieces(board, Piece::Queen);
+
+    let attackers = (attacks::pawn(Side::Black, square) & board.pawns[Side::White])
+        | (attacks::pawn(Side::White, square) & board.pawns[Side::Black])
+        | (attacks::knight(square) & pieces(board, Piece::Knight))
+        | (attacks::king(square) & pieces(board, Piece::King))
+        | (attacks::bishop(square, occupied) & diagonal)
+        | (attacks::rook(square, occupied) & parallel);
+
+    attackers & occupied
+}
+
+/// Static exchange evaluation: the material balance of the capture sequence started by the move
+/// on its target square, assuming both sides always recapture with the least valuable piece and
+/// may stop capturing whenever it would lose material
+pub fn see(board: &Board, m: Move) -> SeeScore {
+    let side = board.side_to_move();
+    let from = m.get_from();
+    let to = m.get_to();
+    let from_mask = Bitboard::from(from);
+    let to_mask = Bitboard::from(to);
+
+    let mut piece = match board.check_piece(side, from_mask) {
+        Some(piece) => piece,
+        None => return 0,
+    };
+
+    let mut occupied = board.any_piece ^ from_mask;
+    let mut gain = [0; 32];
+
+    gain[0] = match board.check_piece(!side, to_mask) {
+        Some(captured) => value(captured),
+        None if piece == Piece::Pawn && (to_mask & board.en_passant).not_empty() => {
+            // the captured pawn is not on the target square
+            occupied ^= Bitboard::from(Square::from_coords(to.file(), from.rank()));
+            value(Piece::Pawn)
+        }
+        None => 0,
+    };
+
+    if m.has_promotion() {
+        piece = Piece::from(m.get_promotion());
+        gain[0] += value(piece) - value(Piece::Pawn);
+    }
+
+    let mut depth = 0;
+    let mut current_side = !side;
+    let mut attacking = attackers(board, to, occupied);
+
+    loop {
+        let side_attackers = attacking & board.occupied[current_side];
+        let next = EXCHANGE_ORDER
+            .iter()
+            .map(|piece| (*piece, side_attackers & pieces(board, *piece)))
+            .find(|(_, mask)| mask.not_empty());
+
+        let (next_piece, next_mask) = match next {
+            Some(next) => next,
+            None => break,
+        };
+
+        // the king cannot capture into an attacked square
+        if next_piece == Piece::King && (attacking & board.occupied[!current_side]).not_empty() {
+            break;
+        }
+
+        depth += 1;
+        if depth >= gain.len() {
+            break;
+        }
+
+        gain[depth] = value(piece) - gain[depth - 1];
+        piece = next_piece;
+
+        let attacker_mask = Bitboard::from(next_mask.peek());
+        occupied ^= attacker_mask;
+        attacking = attackers(board, to, occupied);
+        current_side = !current_side;
+    }
+
+    while depth > 0 {
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+        depth -= 1;
+    }
+
+    gain[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::FenConsumer;
+
+    fn see_run(fen: &str, m: &str, expected: SeeScore) {
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(see(&board, Move::from_uci(m).unwrap()), expected, "{} in {}", m, fen);
+    }
+
+    #[test]
+    fn undefended() {
+        see_run("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5", 100);
+    }
+
+    #[test]
+    fn defended() {
+        see_run("1k6/8/3p4/4p3/8/8/8/1K2Q3 w - - 0 1", "e1e5", 100 - 900);
+        see_run("1k6/8/3p4/4p3/3P4/8/8/1K6 w - - 0 1", "d4e5", 0);
+    }
+
+    #[test]
+    fn x_ray() {
+        // the queen behind the rook joins the exchange once the rook has captured
+        see_run("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5", 100 - 300);
+        see_run("4k3/4r3/8/4p3/8/8/4R3/K3R3 w - - 0 1", "e2e5", 100);
+    }
+
+    #[test]
+    fn quiet() {
+        see_run("1k6/8/3p4/8/8/8/8/1K2Q3 w - - 0 1", "e1e5", -900);
+        see_run("1k6/8/8/8/8/8/8/1K2Q3 w - - 0 1", "e1e5", 0);
+    }
+}
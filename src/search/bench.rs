@@ -0,0 +1,63 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::SystemTime;
+use crate::board::{Board, FenConsumer};
+use crate::search::{Options, Searcher, Signals, SEARCH_STACK_SIZE};
+use crate::transpositions::Transpositions;
+
+pub const BENCH_DEPTH: i16 = 8;
+const BENCH_HASH_SIZE: usize = 16;
+
+const POSITIONS: [&str; 12] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    "rnbqkb1r/pp3ppp/4pn2/2pp4/2PP4/2N2N2/PP2PPPP/R1BQKB1R w KQkq - 0 5",
+    "2r3k1/pp3ppp/4p3/3r4/3P4/P3R3/1P3PPP/4R1K1 b - - 0 24",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+    "8/8/4k3/8/2p5/8/B2K4/8 w - - 0 1",
+    "8/6pk/1p6/8/PP3p1p/5P2/4KP1q/3Q4 w - - 0 1",
+];
+
+pub fn bench(depth: i16) -> u64 {
+    thread::Builder::new()
+        .name(String::from("bench"))
+        .stack_size(SEARCH_STACK_SIZE)
+        .spawn(move || bench_impl(depth))
+        .expect("Cannot spawn bench thread")
+        .join()
+        .expect("Bench thread panicked")
+}
+
+fn bench_impl(depth: i16) -> u64 {
+    let start = SystemTime::now();
+    let mut nodes = 0;
+
+    for (idx, fen) in POSITIONS.iter().enumerate() {
+        output!("info string position {}/{}: {}", idx + 1, POSITIONS.len(), fen);
+        let board = Board::from_fen(fen).expect("invalid bench position");
+        let transpositions = Transpositions::new(BENCH_HASH_SIZE);
+        let mut options = Options::new();
+        options.depth = Some(depth);
+        options.infinite = true;
+        let mut searcher = Searcher::new(board, &transpositions, false, Arc::new(Signals::new()));
+        searcher.go(options);
+        nodes += searcher.nodes();
+    }
+
+    let time_taken = start.elapsed().unwrap();
+    let nps = if time_taken.as_nanos() > 0 {
+        1000000000 * nodes as u128 / time_taken.as_nanos()
+    } else {
+        0
+    };
+    output!("===========================");
+    output!("Total time (ms) : {}", time_taken.as_millis());
+    output!("Nodes searched  : {}", nodes);
+    output!("Nodes/second    : {}", nps);
+    nodes
+}
@@ -309,11 +309,11 @@ impl BookGenerator for Board {
         let mut book = Book::new();
 
         for line in OPENINGS_WHITE {
-            book.add_line_white(self, line.iter().map(|x| Move::from_uci(x)).collect::<Vec<Move>>());
+            book.add_line_white(self, line.iter().map(|x| Move::from_uci(x)).collect::<Result<Vec<Move>, String>>().expect("invalid book line"));
         }
 
         for line in OPENINGS_BLACK {
-            book.add_line_black(self, line.iter().map(|x| Move::from_uci(x)).collect::<Vec<Move>>());
+            book.add_line_black(self, line.iter().map(|x| Move::from_uci(x)).collect::<Result<Vec<Move>, String>>().expect("invalid book line"));
         }
 
         book
@@ -3,11 +3,11 @@ use crate::moves_generation::attacks;
 use crate::search::weights;
 use crate::types::{Bitboard, Piece, Side};
 
-pub type Score = i16;
+pub type Eval = i16;
 
-const SIDE_BONUS_VALUE: Score = 12;
+const SIDE_BONUS_VALUE: Eval = 12;
 
-const fn lerp(phase: i32, a: Score, b: Score) -> Score {
+const fn lerp(phase: i32, a: Eval, b: Eval) -> Eval {
     ((a as i32 * (100 - phase) + b as i32 * phase) / 100) as i16
 }
 
@@ -16,7 +16,7 @@ pub enum Verbosity {
     Verbose,
 }
 
-pub fn evaluate(board: &Board, verbosity: Verbosity) -> Score {
+pub fn evaluate(board: &Board, verbosity: Verbosity) -> Eval {
     match verbosity {
         Verbosity::Quiet => Evaluator::<false>::new(board).evaluate(),
         Verbosity::Verbose => Evaluator::<true>::new(board).evaluate(),
@@ -25,12 +25,12 @@ pub fn evaluate(board: &Board, verbosity: Verbosity) -> Score {
 
 struct Evaluator<'a, const VERBOSE: bool> {
     board: &'a Board,
-    side_multiplier: Score,
+    side_multiplier: Eval,
     doubled_pawns: [i16; 2],
     isolated_pawns: [i16; 2],
 }
 
-fn multiplier(side: Side) -> Score {
+fn multiplier(side: Side) -> Eval {
     match side {
         Side::White => 1,
         Side::Black => -1,
@@ -73,7 +73,7 @@ impl<'a, const VERBOSE: bool> Evaluator<'a, VERBOSE> {
         }
     }
 
-    pub fn evaluate(&mut self) -> Score {
+    pub fn evaluate(&mut self) -> Eval {
         let score_middle = self.evaluate_middle();
         let score_end = self.evaluate_end();
 
@@ -83,25 +83,25 @@ impl<'a, const VERBOSE: bool> Evaluator<'a, VERBOSE> {
         let mobility = self.mobility();
 
         if VERBOSE {
-            println!("score_middle: {}", score_middle);
-            println!("score_end: {}", score_end);
-            println!("endgame_weight: {}", endgame_weight);
-            println!("phase_score: {}", phase_score);
-            println!("side_bonus: {}", side_bonus);
-            println!("mobility: {}", mobility);
+            output!("score_middle: {}", score_middle);
+            output!("score_end: {}", score_end);
+            output!("endgame_weight: {}", endgame_weight);
+            output!("phase_score: {}", phase_score);
+            output!("side_bonus: {}", side_bonus);
+            output!("mobility: {}", mobility);
         }
 
         phase_score + side_bonus + mobility
     }
 
-    fn evaluate_middle(&self) -> Score {
+    fn evaluate_middle(&self) -> Eval {
         let mut score = 0;
         score += self.pieces_score_middle();
         score += self.pawn_score_middle();
         score
     }
 
-    fn evaluate_end(&self) -> Score {
+    fn evaluate_end(&self) -> Eval {
         let mut score = 0;
         score += self.pieces_score_end();
         score += self.pawn_score_end();
@@ -119,24 +119,24 @@ impl<'a, const VERBOSE: bool> Evaluator<'a, VERBOSE> {
         let pieces = ((pieces_white + pieces_black) as i32).clamp(min_bound, max_bound);
 
         if VERBOSE {
-            println!("endgame_weight calculation");
-            println!("-- pieces_white: {}", pieces_white);
-            println!("-- pieces_black: {}", pieces_black);
-            println!("-- pieces: {}", pieces);
-            println!("-- min_bound: {}", min_bound);
-            println!("-- max_bound: {}", max_bound);
+            output!("endgame_weight calculation");
+            output!("-- pieces_white: {}", pieces_white);
+            output!("-- pieces_black: {}", pieces_black);
+            output!("-- pieces: {}", pieces);
+            output!("-- min_bound: {}", min_bound);
+            output!("-- max_bound: {}", max_bound);
         }
 
         ((max_bound - pieces) * 100) / (max_bound - min_bound)
     }
 
     /// Tempo bonus for the side to move
-    fn side_bonus(&self) -> Score {
+    fn side_bonus(&self) -> Eval {
         SIDE_BONUS_VALUE * self.side_multiplier
     }
 
     /// Pieces (non-pawns) base value, side-relative value
-    fn non_pawn_pieces_score(&self, side: Side) -> Score {
+    fn non_pawn_pieces_score(&self, side: Side) -> Eval {
         let mut score = 0;
         for (&source, value) in [
             (&self.board.knights[side], weights::BASE_SCORES[Piece::Knight]),
@@ -149,7 +149,7 @@ impl<'a, const VERBOSE: bool> Evaluator<'a, VERBOSE> {
         score
     }
 
-    fn pieces_score(&self, weight_set: &weights::WeightSet) -> Score {
+    fn pieces_score(&self, weight_set: &weights::WeightSet) -> Eval {
         let mut score = 0;
         for side in [Side::White, Side::Black] {
             let multiplier = multiplier(side);
@@ -162,22 +162,22 @@ impl<'a, const VERBOSE: bool> Evaluator<'a, VERBOSE> {
     }
 
     /// Middle-game pieces score calculated from base pieces score and PSQT
-    fn pieces_score_middle(&self) -> Score {
+    fn pieces_score_middle(&self) -> Eval {
         if VERBOSE {
-            println!("calculating pieces_score_middle");
+            output!("calculating pieces_score_middle");
         }
         self.pieces_score(&weights::MID_GAME)
     }
 
     /// Endgame pieces score calculated from base pieces score and PSQT
-    fn pieces_score_end(&self) -> Score {
+    fn pieces_score_end(&self) -> Eval {
         if VERBOSE {
-            println!("calculating pieces_score_end");
+            output!("calculating pieces_score_end");
         }
         self.pieces_score(&weights::END_GAME)
     }
 
-    fn pawn_score_middle(&self) -> Score {
+    fn pawn_score_middle(&self) -> Eval {
         let mut score = 0;
 
         for side in [Side::White, Side::Black] {
@@ -192,7 +192,7 @@ impl<'a, const VERBOSE: bool> Evaluator<'a, VERBOSE> {
         score
     }
 
-    fn pawn_score_end(&self) -> Score {
+    fn pawn_score_end(&self) -> Eval {
         let mut score = 0;
 
         for side in [Side::White, Side::Black] {
@@ -207,11 +207,11 @@ impl<'a, const VERBOSE: bool> Evaluator<'a, VERBOSE> {
         score
     }
 
-    fn piece_mobility(&self, attacks: Bitboard, mask: Bitboard) -> Score {
-        (attacks & mask).pieces() as Score
+    fn piece_mobility(&self, attacks: Bitboard, mask: Bitboard) -> Eval {
+        (attacks & mask).pieces() as Eval
     }
 
-    fn mobility(&self) -> Score {
+    fn mobility(&self) -> Eval {
         let mut score = 0;
 
         for side in [Side::White, Side::Black] {
@@ -0,0 +1,87 @@
+use crate::moves_generation::{HistorySet, PieceToHistory};
+use crate::search::stack::StackEntry;
+use crate::types::{Move, Piece, Side};
+
+pub const MAX_HISTORY: i32 = 16384;
+
+pub fn piece_index(side: Side, piece: Piece) -> usize {
+    side as usize * 6 + piece as usize
+}
+
+/// Moves the entry towards the bonus, the closer it already is to the limit the slower it goes
+fn gravity(entry: &mut i32, bonus: i32) {
+    *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+}
+
+pub struct History {
+    butterfly: [HistorySet; 2],
+    counter_moves: [[Move; 64]; 12],
+    // indexed by the piece and the target square of the previous move
+    continuation: Box<[PieceToHistory]>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            butterfly: [[[0; 64]; 64]; 2],
+            counter_moves: [[Move::new(); 64]; 12],
+            continuation: vec![[[0; 64]; 12]; 12 * 64].into_boxed_slice(),
+        }
+    }
+
+    pub fn butterfly(&self, side: Side) -> &HistorySet {
+        &self.butterfly[side]
+    }
+
+    pub fn counter_move(&self, previous: &StackEntry) -> Option<Move> {
+        let piece = previous.piece?;
+        let m = self.counter_moves[piece_index(previous.side, piece)][previous.m.get_to()];
+        match m == Move::new() {
+            true => None,
+            false => Some(m),
+        }
+    }
+
+    pub fn continuation(&self, previous: &StackEntry) -> Option<&PieceToHistory> {
+        let piece = previous.piece?;
+        Some(&self.continuation[piece_index(previous.side, piece) * 64 + previous.m.get_to() as usize])
+    }
+
+    pub fn set_counter_move(&mut self, previous: &StackEntry, m: Move) {
+        if let Some(piece) = previous.piece {
+            self.counter_moves[piece_index(previous.side, piece)][previous.m.get_to()] = m;
+        }
+    }
+
+    /// Rewards (positive bonus) or penalizes (negative bonus) a quiet move, both in the butterfly
+    /// table and in the continuation tables of the given previous moves
+    pub fn update(&mut self, side: Side, piece: Piece, m: Move, previous: &[Option<StackEntry>], bonus: i32) {
+        gravity(&mut self.butterfly[side][m.get_from()][m.get_to()], bonus);
+
+        for entry in previous.iter().flatten() {
+            if let Some(previous_piece) = entry.piece {
+                let idx = piece_index(entry.side, previous_piece) * 64 + entry.m.get_to() as usize;
+                gravity(&mut self.continuation[idx][piece_index(side, piece)][m.get_to()], bonus);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gravity_bounded() {
+        let mut entry = 0;
+        for _ in 0..1000 {
+            gravity(&mut entry, MAX_HISTORY);
+        }
+        assert_eq!(entry, MAX_HISTORY);
+
+        for _ in 0..1000 {
+            gravity(&mut entry, -400);
+        }
+        assert!((-MAX_HISTORY..0).contains(&entry));
+    }
+}
@@ -1,13 +1,32 @@
+mod bench;
 mod book;
 mod checks;
 mod eval;
+mod history;
 mod options;
+mod params;
+mod root_move;
+mod score;
 mod search;
+mod signals;
+mod skill;
+mod stack;
+mod time;
+mod wdl;
 mod weights;
 
-pub use options::Options;
+pub use bench::{bench, BENCH_DEPTH};
+pub use history::piece_index;
+pub use options::{Options, Output};
+#[cfg(feature = "tune")]
+pub use params::{find as find_parameter, PARAMETERS};
 pub use search::KILLER_MOVES_STORED;
+pub use search::SEARCH_STACK_SIZE;
 pub use search::Searcher;
-pub use eval::Score;
+pub use signals::Signals;
+pub use skill::{level_from_elo, MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO};
+pub use time::{DEFAULT_MOVE_OVERHEAD, MAX_MOVE_OVERHEAD};
+pub use wdl::WdlModel;
+pub use score::Score;
 pub use eval::Verbosity;
 pub use eval::evaluate;
@@ -1,12 +1,33 @@
+use crate::search::{skill, time};
+use crate::types::Move;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Output {
+    Uci,
+    Xboard,
+    Silent,
+}
+
+#[derive(Clone)]
 pub struct Options {
     pub white_time: i32,
     pub black_time: i32,
-    #[allow(dead_code)]
     pub white_increment: i32,
-    #[allow(dead_code)]
     pub black_increment: i32,
     pub target_time: Option<i32>,
     pub depth: Option<i16>,
+    pub nodes: Option<u64>,
+    pub mate: Option<i16>,
+    pub moves_to_go: Option<i32>,
+    pub move_overhead: i32,
+    pub search_moves: Vec<Move>,
+    pub multi_pv: usize,
+    pub threads: usize,
+    pub skill_level: f64,
+    pub infinite: bool,
+    pub ponder: bool,
+    pub output: Output,
+    pub show_wdl: bool,
 }
 
 impl Options {
@@ -18,6 +39,18 @@ impl Options {
             black_increment: 0,
             target_time: None,
             depth: None,
+            nodes: None,
+            mate: None,
+            moves_to_go: None,
+            move_overhead: time::DEFAULT_MOVE_OVERHEAD,
+            search_moves: Vec::new(),
+            multi_pv: 1,
+            threads: 1,
+            skill_level: skill::MAX_SKILL_LEVEL,
+            infinite: false,
+            ponder: false,
+            output: Output::Uci,
+            show_wdl: false,
         }
     }
 }
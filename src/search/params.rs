@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// Search constant which can be changed at runtime (by the tuning builds)
+pub struct Parameter {
+    pub name: &'static str,
+    #[cfg_attr(not(feature = "tune"), allow(dead_code))]
+    pub default: i32,
+    pub min: i32,
+    pub max: i32,
+    value: AtomicI32,
+}
+
+impl Parameter {
+    const fn new(name: &'static str, default: i32, min: i32, max: i32) -> Self {
+        Self { name, default, min, max, value: AtomicI32::new(default) }
+    }
+
+    pub fn get(&self) -> i32 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    #[cfg_attr(not(feature = "tune"), allow(dead_code))]
+    pub fn set(&self, value: i32) -> Result<(), String> {
+        if value < self.min || value > self.max {
+            return Err(format!("{} must be between {} and {}", self.name, self.min, self.max));
+        }
+        self.value.store(value, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+pub static ASPIRATION_WINDOW: Parameter = Parameter::new("AspirationWindow", 40, 5, 200);
+pub static RAZORING_MARGIN: Parameter = Parameter::new("RazoringMargin", 500, 0, 1500);
+pub static RAZORING_DEPTH_MARGIN: Parameter = Parameter::new("RazoringDepthMargin", 200, 0, 600);
+pub static NULL_MOVE_REDUCTION: Parameter = Parameter::new("NullMoveReduction", 1, 0, 4);
+pub static NULL_MOVE_DEPTH_PERCENT: Parameter = Parameter::new("NullMoveDepthPercent", 67, 0, 100);
+pub static LMR_MIN_DEPTH_FROM_ROOT: Parameter = Parameter::new("LmrMinDepthFromRoot", 3, 0, 10);
+pub static LMR_MIN_MOVES: Parameter = Parameter::new("LmrMinMoves", 4, 0, 20);
+pub static LMR_DEEP_MOVES: Parameter = Parameter::new("LmrDeepMoves", 12, 1, 64);
+pub static DELTA_MARGIN: Parameter = Parameter::new("DeltaMargin", 900, 0, 2000);
+pub static SEE_PRUNING_DEPTH: Parameter = Parameter::new("SeePruningDepth", 3, 0, 10);
+pub static SEE_QUIET_MARGIN: Parameter = Parameter::new("SeeQuietMargin", 80, 0, 500);
+pub static SINGULAR_MIN_DEPTH: Parameter = Parameter::new("SingularMinDepth", 6, 1, 20);
+pub static SINGULAR_MARGIN: Parameter = Parameter::new("SingularMargin", 2, 0, 20);
+
+#[cfg_attr(not(feature = "tune"), allow(dead_code))]
+pub static PARAMETERS: [&Parameter; 13] = [
+    &ASPIRATION_WINDOW,
+    &RAZORING_MARGIN,
+    &RAZORING_DEPTH_MARGIN,
+    &NULL_MOVE_REDUCTION,
+    &NULL_MOVE_DEPTH_PERCENT,
+    &LMR_MIN_DEPTH_FROM_ROOT,
+    &LMR_MIN_MOVES,
+    &LMR_DEEP_MOVES,
+    &DELTA_MARGIN,
+    &SEE_PRUNING_DEPTH,
+    &SEE_QUIET_MARGIN,
+    &SINGULAR_MIN_DEPTH,
+    &SINGULAR_MARGIN,
+];
+
+#[cfg_attr(not(feature = "tune"), allow(dead_code))]
+pub fn find(name: &str) -> Option<&'static Parameter> {
+    PARAMETERS.iter().find(|parameter| parameter.name == name).copied()
+}
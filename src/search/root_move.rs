@@ -0,0 +1,25 @@
+use crate::search::Score;
+use crate::types::Move;
+
+pub const UNKNOWN_SCORE: Score = Score::MIN;
+
+#[derive(Clone)]
+pub struct RootMove {
+    pub m: Move,
+    pub score: Score,
+    pub previous_score: Score,
+    pub pv: Vec<Move>,
+    pub nodes: u64,
+}
+
+impl RootMove {
+    pub fn new(m: Move) -> Self {
+        Self {
+            m,
+            score: UNKNOWN_SCORE,
+            previous_score: UNKNOWN_SCORE,
+            pv: vec![m],
+            nodes: 0,
+        }
+    }
+}
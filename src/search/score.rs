@@ -0,0 +1,145 @@
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Neg, Sub};
+use crate::search::eval::Eval;
+
+const MATE: i16 = 10000;
+// mates further away than this cannot be found, so anything above is a mate score
+const MATE_BOUND: i16 = MATE - 256;
+
+/// Search result from the side to move point of view: either centipawns or a mate in some
+/// number of plies, counted from the root
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Score(i16);
+
+impl Score {
+    pub const ZERO: Score = Score(0);
+    pub const INFINITE: Score = Score(i16::MAX);
+    // the negation of the infinity has to fit in the type as well
+    pub const MIN: Score = Score(-i16::MAX);
+
+    pub const fn new(centipawns: Eval) -> Self {
+        Self(centipawns)
+    }
+
+    /// The side to move mates in the given number of plies
+    pub const fn mate_in(plies: i16) -> Self {
+        Self(MATE - plies)
+    }
+
+    /// The side to move gets mated in the given number of plies
+    pub const fn mated_in(plies: i16) -> Self {
+        Self(plies - MATE)
+    }
+
+    pub fn is_mate(&self) -> bool {
+        self.0.abs() >= MATE_BOUND
+    }
+
+    /// Plies to the mate, negative when the side to move is the one getting mated
+    pub fn mate_in_plies(&self) -> Option<i16> {
+        match self.is_mate() {
+            true => Some(self.0.signum() * (MATE - self.0.abs())),
+            false => None,
+        }
+    }
+
+    /// Moves to the mate, as given in the UCI "score mate" output
+    pub fn mate_in_moves(&self) -> Option<i16> {
+        self.mate_in_plies().map(|plies| match plies > 0 {
+            true => (plies + 1) / 2,
+            false => plies / 2,
+        })
+    }
+
+    pub fn centipawns(&self) -> i16 {
+        self.0
+    }
+
+    /// The table keeps the mate distance from the stored position rather than from the root,
+    /// so that the entry stays valid when the position is reached at another ply; the window
+    /// bounds beyond the mate range are left as they are
+    pub fn to_table(self, ply: i16) -> Self {
+        match self.0 {
+            score if (MATE_BOUND..=MATE).contains(&score) => Self(score + ply),
+            score if (-MATE..=-MATE_BOUND).contains(&score) => Self(score - ply),
+            _ => self,
+        }
+    }
+
+    pub fn to_search(self, ply: i16) -> Self {
+        match self.0 {
+            score if (MATE_BOUND..=MATE).contains(&score) => Self(score - ply),
+            score if (-MATE..=-MATE_BOUND).contains(&score) => Self(score + ply),
+            _ => self,
+        }
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+
+    fn neg(self) -> Self::Output {
+        Self(-self.0)
+    }
+}
+
+impl Add<i16> for Score {
+    type Output = Score;
+
+    fn add(self, rhs: i16) -> Self::Output {
+        Self(self.0.saturating_add(rhs))
+    }
+}
+
+impl Sub<i16> for Score {
+    type Output = Score;
+
+    fn sub(self, rhs: i16) -> Self::Output {
+        Self(self.0.saturating_sub(rhs))
+    }
+}
+
+impl From<Score> for i32 {
+    fn from(score: Score) -> Self {
+        score.0 as i32
+    }
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.mate_in_moves() {
+            Some(moves) => write!(f, "mate {}", moves),
+            None => write!(f, "cp {}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mates() {
+        assert_eq!(Score::mate_in(1).mate_in_moves(), Some(1));
+        assert_eq!(Score::mate_in(3).mate_in_moves(), Some(2));
+        assert_eq!(Score::mated_in(2).mate_in_moves(), Some(-1));
+        assert_eq!(Score::mated_in(4).mate_in_plies(), Some(-4));
+        assert_eq!(-Score::mate_in(5), Score::mated_in(5));
+        assert!(!Score::new(900).is_mate());
+        assert_eq!(Score::new(-35).to_string(), "cp -35");
+        assert_eq!(Score::mated_in(6).to_string(), "mate -3");
+    }
+
+    #[test]
+    fn table_conversion() {
+        // mate in 5 plies from the root, found at ply 3, is a mate in 2 plies from the stored position
+        let stored = Score::mate_in(5).to_table(3);
+        assert_eq!(stored, Score::mate_in(2));
+        // reaching the same position at ply 7 makes it a mate in 9 plies from the root
+        assert_eq!(stored.to_search(7), Score::mate_in(9));
+        assert_eq!(Score::mated_in(6).to_table(2).to_search(4), Score::mated_in(8));
+        assert_eq!(Score::new(120).to_table(10), Score::new(120));
+        assert_eq!(Score::INFINITE.to_table(5), Score::INFINITE);
+        assert_eq!(Score::MIN.to_search(5), Score::MIN);
+    }
+}
@@ -1,37 +1,79 @@
-use std::cmp::{max, min};
+use std::cmp::{max, min, Reverse};
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, SystemTime};
 use rand::Rng;
 use rand::seq::SliceRandom;
 use crate::board::{Board, FenProducer};
 use crate::moves_generation;
-use crate::moves_generation::MoveList;
+use crate::moves_generation::{MoveList, Moves, QuietHistory};
 use crate::search::checks::Checks;
-use crate::search::eval::{Score, Verbosity};
-use crate::search::{book, eval, weights, Options};
+use crate::search::eval::Verbosity;
+use crate::search::history::{History, MAX_HISTORY};
+use crate::search::root_move::{RootMove, UNKNOWN_SCORE};
+use crate::search::skill::{self, Skill};
+use crate::search::stack::StackEntry;
+use crate::search::time::{Stability, TimeLimits};
+use crate::search::{book, eval, params, wdl, weights, Options, Output, Score, Signals};
 use crate::transpositions::{TableScore, Transpositions};
-use crate::types::{Bitboard, Move, Piece, Side};
+use crate::types::{Bitboard, Move, Piece};
 
 const NULL_MOVE: Move = Move::new();
 const MAX_DEPTH: i16 = 64;
+const MAX_PLY: usize = 128;
 pub const KILLER_MOVES_STORED: usize = 3;
+pub const SEARCH_STACK_SIZE: usize = 64 * 1024 * 1024;
 
-const ALL_MOVES: bool = false;
-const CAPTURES_ONLY: bool = true;
+// Helper threads skip some of the iterations, so that they are spread across different depths
+const SKIP_SIZE: [i16; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [i16; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+enum Singularity {
+    None,
+    Extend(Move),
+    Cut(Score),
+}
+
+impl Singularity {
+    fn extension(&self, m: Move) -> i16 {
+        match self {
+            Singularity::Extend(singular) if *singular == m => 1,
+            _ => 0,
+        }
+    }
+}
+
+pub struct BestMove {
+    pub m: Move,
+    pub ponder: Option<Move>,
+}
 
 pub struct Searcher<'a> {
     board: Board,
-    transpositions: &'a mut Transpositions,
+    transpositions: &'a Transpositions,
 
     book: bool,
 
     depth: i16,
     seldepth: i16,
 
-    last_eval: Score,
-    best_move: Move,
+    root_moves: Vec<RootMove>,
+    pv_index: usize,
+    multi_pv: usize,
+    output: Output,
+    show_wdl: bool,
     killers: [[Move; KILLER_MOVES_STORED]; MAX_DEPTH as usize],
-    history: [[[u32; 64]; 64]; 2],
+    history: History,
+    stack: [StackEntry; MAX_PLY],
+    pv: [[Move; MAX_PLY]; MAX_PLY],
+    pv_length: [usize; MAX_PLY],
 
     nodes: u64,
     tbhits: u64,
@@ -44,12 +86,18 @@ pub struct Searcher<'a> {
 
     clock_queries: usize,
     start_time: SystemTime,
-    target_time: u128,
+    time_limits: TimeLimits,
+    target_nodes: u64,
     time_hit: bool,
+    signals: Arc<Signals>,
+
+    helper_id: usize,
+    helper_signals: Option<Arc<Signals>>,
+    published_nodes: u64,
 }
 
 impl<'a> Searcher<'a> {
-    pub fn new(board: Board, transpositions: &'a mut Transpositions, book: bool) -> Self {
+    pub fn new(board: Board, transpositions: &'a Transpositions, book: bool, signals: Arc<Signals>) -> Self {
         Self {
             board,
             transpositions,
@@ -59,10 +107,16 @@ impl<'a> Searcher<'a> {
             depth: 0,
             seldepth: 0,
 
-            last_eval: 0,
-            best_move: NULL_MOVE,
+            root_moves: Vec::new(),
+            pv_index: 0,
+            multi_pv: 1,
+            output: Output::Uci,
+            show_wdl: false,
             killers: [[NULL_MOVE; KILLER_MOVES_STORED]; MAX_DEPTH as usize],
-            history: [[[0; 64]; 64]; 2],
+            history: History::new(),
+            stack: [StackEntry::new(); MAX_PLY],
+            pv: [[NULL_MOVE; MAX_PLY]; MAX_PLY],
+            pv_length: [0; MAX_PLY],
 
             nodes: 0,
             tbhits: 0,
@@ -75,46 +129,84 @@ impl<'a> Searcher<'a> {
 
             clock_queries: 0,
             start_time: SystemTime::now(),
-            target_time: 0,
+            time_limits: TimeLimits::INFINITE,
+            target_nodes: u64::MAX,
             time_hit: false,
+            signals,
+
+            helper_id: 0,
+            helper_signals: None,
+            published_nodes: 0,
         }
     }
 
-    fn get_pv(&mut self, limit: i16) -> String {
-        if limit <= 0 {
-            return String::new();
+    pub fn nodes(&self) -> u64 {
+        self.total_nodes()
+    }
+
+    fn is_main(&self) -> bool {
+        self.helper_id == 0
+    }
+
+    fn total_nodes(&self) -> u64 {
+        self.nodes + self.helper_signals.as_ref().map_or(0, |signals| signals.nodes())
+    }
+
+    fn publish_nodes(&mut self) {
+        if !self.is_main() {
+            self.signals.add_nodes(self.nodes - self.published_nodes);
+            self.published_nodes = self.nodes;
         }
+    }
 
-        let moves = moves_generation::generate_all(&self.board);
-        let best = self.transpositions.get_move(self.board.key());
-
-        match best {
-            Some(m) if moves.contains(&m) => {
-                self.board.make_move(m);
-                let result = format!(" {:?}{}", m, self.get_pv(limit - 1));
-                self.board.unmake_move();
-                result
-            },
-            _ => String::new(),
+    fn clear_pv(&mut self, ply: i16) {
+        if (ply as usize) < MAX_PLY {
+            self.pv_length[ply as usize] = 0;
         }
     }
 
-    fn print_search_info(&mut self, current_depth: i16, score: Score, pv: &str, aspiration_fail: bool) {
+    /// Makes the move followed by the principal variation found one ply deeper the principal
+    /// variation at the given ply
+    fn update_pv(&mut self, ply: i16, m: Move) {
+        let ply = ply as usize;
+        if ply + 1 >= MAX_PLY {
+            return;
+        }
+
+        let child_length = self.pv_length[ply + 1].min(MAX_PLY - 1);
+        let (current, child) = self.pv.split_at_mut(ply + 1);
+        current[ply][0] = m;
+        current[ply][1..=child_length].copy_from_slice(&child[0][..child_length]);
+        self.pv_length[ply] = child_length + 1;
+    }
+
+    fn print_search_info(&mut self, current_depth: i16, line: usize, score: Score, bound: Bound, pv: &[Move], aspiration_fail: bool) {
         let time = self.start_time.elapsed().unwrap();
-        println!(
-            "info depth {} seldepth {} score {} nodes {} nps {} time {} hashfull {} tbhits {} pv{} string nodes_n {} nodes_z {} nodes_q {} dprunes {} asp_retry {} razor att {} succ {}",
+        let nodes = self.total_nodes();
+        let pv = pv.iter().map(|m| format!(" {:?}", m)).collect::<String>();
+        match self.output {
+            Output::Silent => return,
+            Output::Xboard if bound != Bound::Exact => return,
+            Output::Xboard => {
+                self.print_xboard_info(current_depth, score, time.as_millis() / 10, nodes, &pv);
+                return;
+            }
+            Output::Uci => (),
+        }
+        output!(
+            "info depth {} seldepth {} multipv {} score {}{}{} nodes {} nps {} time {} hashfull {} tbhits {} pv{} string nodes_n {} nodes_z {} nodes_q {} dprunes {} asp_retry {} razor att {} succ {}",
             current_depth,
             self.seldepth.max(current_depth),
-            if score.abs() > 9000 {
-                format!("mate {}", score.signum() * (1 + (10000 - score.abs())) / 2)
-            } else {
-                format!("cp {}", score * match self.board.side_to_move() {
-                    Side::White => 1,
-                    Side::Black => -1,
-                })
+            line + 1,
+            score,
+            match bound {
+                Bound::Exact => "",
+                Bound::Lower => " lowerbound",
+                Bound::Upper => " upperbound",
             },
-            self.nodes,
-            1000000000 * self.nodes as u128 / max(1, time.as_nanos()),
+            self.wdl_info(score),
+            nodes,
+            1000000000 * nodes as u128 / max(1, time.as_nanos()),
             time.as_millis(),
             self.transpositions.usage(),
             self.tbhits,
@@ -129,18 +221,49 @@ impl<'a> Searcher<'a> {
         );
     }
 
+    fn wdl_info(&self, score: Score) -> String {
+        if !self.show_wdl {
+            return String::new();
+        }
+
+        let wdl = match score.mate_in_plies() {
+            Some(plies) if plies > 0 => wdl::Wdl { win: 1000, draw: 0, loss: 0 },
+            Some(_) => wdl::Wdl { win: 0, draw: 0, loss: 1000 },
+            None => wdl::DEFAULT_MODEL.wdl(score.centipawns(), wdl::material(&self.board)),
+        };
+        format!(" wdl {} {} {}", wdl.win, wdl.draw, wdl.loss)
+    }
+
+    fn print_xboard_info(&self, current_depth: i16, score: Score, centiseconds: u128, nodes: u64, pv: &str) {
+        let score = match score.mate_in_moves() {
+            Some(moves) => moves.signum() as i32 * (100000 + moves.abs() as i32),
+            None => score.centipawns() as i32,
+        };
+        output!("{} {} {} {}{}", current_depth, score, centiseconds, nodes, pv);
+    }
+
     fn out_of_time(&mut self) -> bool {
         if self.time_hit {
             return true;
         }
 
+        if self.total_nodes() >= self.target_nodes {
+            self.time_hit = true;
+            return true;
+        }
+
         // profiler actually said that this was quite costly, but since we are processing
         // millions of nodes per second, checking the clock once every 1000th is probably
         // acceptable
         self.clock_queries += 1;
         if self.clock_queries > 1000 {
             self.clock_queries = 0;
-            if self.start_time.elapsed().unwrap().as_millis() >= self.target_time {
+            self.publish_nodes();
+            if self.signals.stopped() {
+                self.time_hit = true;
+                return true;
+            }
+            if !self.signals.pondering() && self.start_time.elapsed().unwrap().as_millis() >= self.time_limits.maximum {
                 self.time_hit = true;
                 return true;
             }
@@ -149,30 +272,14 @@ impl<'a> Searcher<'a> {
         false
     }
 
-    fn calculate_target_time(&self, options: &Options) -> u128 {
-        if let Some(requested_time) = options.target_time {
-            return requested_time as u128 - 100;
-        }
-
-        let side = self.board.side_to_move();
-        let our_time = side.choose(options.white_time, options.black_time);
-        let opponent_time = (!side).choose(options.white_time, options.black_time);
-        let time_advantage = our_time - opponent_time;
-        let time_advantage_modifier = if time_advantage > 0 { time_advantage / 4 } else { time_advantage / 8 };
-        let divider = match self.board.full_moves_count {
-            m if m < 2 => 60,
-            m if m < 4 => 25,
-            m if m < 6 => 12,
-            _ => 8,
-        } * if side.choose(options.white_increment, options.black_increment) > 0 { 1 } else { 2 };
-
-        let result = (our_time / divider + max(0, time_advantage_modifier)) as u128;
+    fn calculate_time_limits(&self, options: &Options) -> TimeLimits {
+        let limits = TimeLimits::new(options, self.board.side_to_move());
 
-        println!(
-            "info string our time: {} opponent time: {} time advantage: {} advantage modifier: {} moves count: {} divider: {} target time: {}",
-            our_time, opponent_time, time_advantage, time_advantage_modifier, self.board.full_moves_count, divider, result);
+        if self.output == Output::Uci && self.is_main() && limits != TimeLimits::INFINITE {
+            output!("info string optimum time: {} maximum time: {}", limits.optimum, limits.maximum);
+        }
 
-        result
+        limits
     }
 
     fn get_book_move(&self) -> Option<Move> {
@@ -187,7 +294,7 @@ impl<'a> Searcher<'a> {
                 let mut rng = rand::thread_rng();
                 if let Some(m) = legal_moves.choose(&mut rng) {
                     thread::sleep(Duration::from_millis(rng.gen_range(50..100)));
-                    println!("info depth 1 score cp 0");
+                    output!("info depth 1 score cp 0");
                     return Some(m.clone());
                 }
             }
@@ -196,36 +303,87 @@ impl<'a> Searcher<'a> {
         None
     }
 
-    fn get_moves<const CAPTURES_ONLY: bool>(&mut self, depth: i16) -> MoveList {
-        let moves = if CAPTURES_ONLY {
-            moves_generation::generate_captures(&self.board)
-        } else {
-            moves_generation::generate_all(&self.board)
+    fn get_moves(&mut self, ply: i16, depth: i16) -> MoveList {
+        let moves = moves_generation::generate_all(&self.board);
+        self.order_moves(moves, ply, depth)
+    }
+
+    // extensions can take the remaining depth past the size of the killers table
+    fn killer_index(depth: i16) -> usize {
+        depth.clamp(0, MAX_DEPTH - 1) as usize
+    }
+
+    fn order_moves(&mut self, moves: Moves, ply: i16, depth: i16) -> MoveList {
+        let previous = self.previous_moves(ply);
+        let history = QuietHistory {
+            butterfly: self.history.butterfly(self.board.side_to_move()),
+            counter_move: previous[0].and_then(|entry| self.history.counter_move(&entry)),
+            continuations: previous.map(|entry| entry.and_then(|entry| self.history.continuation(&entry))),
         };
-        let killer_table_depth = if (depth >= 0) && (depth < (MAX_DEPTH - 1)) { depth } else { MAX_DEPTH - 1 } as usize;
         let weights = moves_generation::order(
             &self.board,
             &moves,
             self.transpositions.get_move(self.board.key()),
-            &self.killers[killer_table_depth],
-            &self.history[self.board.side_to_move()]);
+            &self.killers[Self::killer_index(depth)],
+            &history);
         MoveList::new(moves, weights)
     }
 
-    fn break_conditions(&mut self, depth: i16, alpha: Score, beta: Score, root: bool) -> Option<Score> {
+    /// Stack entries of the last two moves leading to the given ply, none if it was a null move
+    fn previous_moves(&self, ply: i16) -> [Option<StackEntry>; 2] {
+        let ply = ply as usize;
+        let entry = |distance: usize| match ply >= distance && ply - distance < MAX_PLY {
+            true => Some(self.stack[ply - distance]).filter(|entry| entry.piece.is_some()),
+            false => None,
+        };
+        [entry(1), entry(2)]
+    }
+
+    fn push_move(&mut self, ply: i16, m: Move) {
+        if let Some(entry) = self.stack.get_mut(ply as usize) {
+            let side = self.board.side_to_move();
+            entry.m = m;
+            entry.side = side;
+            entry.piece = self.board.check_piece(side, Bitboard::from(m.get_from()));
+        }
+    }
+
+    fn push_null(&mut self, ply: i16) {
+        for entry in self.stack.iter_mut().skip(ply as usize).take(2) {
+            entry.m = NULL_MOVE;
+            entry.piece = None;
+        }
+    }
+
+    /// Static evaluation from the side to move point of view
+    fn evaluate(&self) -> Score {
+        Score::new(eval::evaluate(&self.board, Verbosity::Quiet) * self.board.side_to_move().choose(1, -1))
+    }
+
+    fn static_eval(&self, ply: i16) -> Option<Score> {
+        self.stack.get(ply as usize).and_then(|entry| entry.static_eval)
+    }
+
+    fn set_static_eval(&mut self, ply: i16, static_eval: Option<Score>) {
+        if let Some(entry) = self.stack.get_mut(ply as usize) {
+            entry.static_eval = static_eval;
+        }
+    }
+
+    fn break_conditions(&mut self, ply: i16, depth: i16, alpha: Score, beta: Score, root: bool) -> Option<Score> {
         if root {
             return None;
         }
 
         if self.out_of_time() {
-            return Some(0);
+            return Some(Score::ZERO);
         }
 
         if self.board.draw_conditions() {
-            return Some(0);
+            return Some(Score::ZERO);
         }
 
-        if let Some(score) = self.transpositions.get(self.board.key(), depth, alpha, beta) {
+        if let Some(score) = self.transpositions.get(self.board.key(), ply, depth, alpha, beta) {
             self.tbhits += 1;
             return Some(score);
         }
@@ -233,44 +391,63 @@ impl<'a> Searcher<'a> {
         None
     }
 
-    fn checkmate_score(&self, ply: i16) -> Score {
-        -(10000 - ply)
-    }
-
     fn no_moves_conditions(&mut self, ply: i16, moves: &MoveList) -> Option<Score> {
         match moves.is_empty() {
             false => None,
             true => Some(match self.board.in_check() {
-                false => 0, // stalemate
-                true => self.checkmate_score(ply), // checkmate in N
+                false => Score::ZERO, // stalemate
+                true => Score::mated_in(ply), // checkmate in N
             })
         }
     }
 
     fn late_move_reduction(&mut self, depth: i16, m: Move, move_counter: i32) -> i16 {
         let depth_from_root = self.depth - depth;
-        if depth_from_root > 3 && move_counter > 4
-            && !self.killers[depth as usize].contains(&m)
+        if depth_from_root > params::LMR_MIN_DEPTH_FROM_ROOT.get() as i16
+            && move_counter > params::LMR_MIN_MOVES.get()
+            && !self.killers[Self::killer_index(depth)].contains(&m)
             && !self.board.in_check() {
-            return if move_counter < 12 { 1 } else { 2 };
+            return if move_counter < params::LMR_DEEP_MOVES.get() { 1 } else { 2 };
         }
         0
     }
 
-    fn store_killer(&mut self, depth: i16, m: Move) {
-        if (self.board.any_piece & Bitboard::from(m.get_to())).not_empty() {
+    fn is_quiet(&self, m: Move) -> bool {
+        let to = Bitboard::from(m.get_to());
+        let en_passant = to == self.board.en_passant
+            && (self.board.pawns[self.board.current_color] & Bitboard::from(m.get_from())).not_empty();
+        !m.has_promotion() && !en_passant && (self.board.any_piece & to).empty()
+    }
+
+    /// Rewards the quiet move which caused a cutoff and penalizes the quiet moves tried before it
+    fn update_quiet_stats(&mut self, ply: i16, depth: i16, m: Move, quiets: &[Move]) {
+        if !self.is_quiet(m) {
             return;
         }
 
-        let depth = depth as usize;
-
         let side = self.board.side_to_move();
-        let from = m.get_from();
-        let to = m.get_to();
-        let history_bonus = (depth as u32 * depth as u32).clamp(1, 16384);
-        let current_history = self.history[side][from][to];
-        let history_penalty = (current_history * history_bonus) / 16384;
-        self.history[side][from][to] += history_bonus - history_penalty;
+        let previous = self.previous_moves(ply);
+        let bonus = (depth as i32 * depth as i32).clamp(1, MAX_HISTORY);
+
+        for &quiet in quiets.iter().filter(|&&quiet| quiet != m) {
+            if let Some(piece) = self.board.check_piece(side, Bitboard::from(quiet.get_from())) {
+                self.history.update(side, piece, quiet, &previous, -bonus);
+            }
+        }
+
+        if let Some(piece) = self.board.check_piece(side, Bitboard::from(m.get_from())) {
+            self.history.update(side, piece, m, &previous, bonus);
+        }
+
+        if let Some(entry) = previous[0] {
+            self.history.set_counter_move(&entry, m);
+        }
+
+        self.store_killer(depth, m);
+    }
+
+    fn store_killer(&mut self, depth: i16, m: Move) {
+        let depth = Self::killer_index(depth);
 
         if self.killers[depth].contains(&m) {
             return;
@@ -280,9 +457,50 @@ impl<'a> Searcher<'a> {
         self.killers[depth][0] = m;
     }
 
+    fn excluded(&self, ply: i16) -> Option<Move> {
+        self.stack.get(ply as usize).and_then(|entry| entry.excluded)
+    }
+
+    /// Checks whether the move from the transposition table is much better than all the other
+    /// moves, by searching them with a lowered window and a reduced depth. When even the lowered
+    /// window is above beta, several moves are good enough to cut off the whole node.
+    fn singular_search(&mut self, ply: i16, depth: i16, beta: Score) -> Singularity {
+        if depth < params::SINGULAR_MIN_DEPTH.get() as i16 || (ply as usize) >= MAX_PLY || self.excluded(ply).is_some() {
+            return Singularity::None;
+        }
+
+        let probe = match self.transpositions.probe(self.board.key(), ply) {
+            Some(probe) if probe.m != NULL_MOVE && probe.depth >= depth - 3 => probe,
+            _ => return Singularity::None,
+        };
+
+        let tt_score = match probe.score {
+            TableScore::Exact(score) | TableScore::LowerBound(score) => score,
+            TableScore::UpperBound(_) => return Singularity::None,
+        };
+
+        let singular_beta = tt_score - (params::SINGULAR_MARGIN.get() * depth as i32) as i16;
+
+        self.stack[ply as usize].excluded = Some(probe.m);
+        let score = self.zero_window(ply, (depth - 1) / 2, singular_beta, false);
+        self.stack[ply as usize].excluded = None;
+
+        if self.time_hit {
+            Singularity::None
+        } else if score < singular_beta && ply < 2 * self.depth {
+            // only lines shorter than twice the iteration depth are extended, so that the extensions cannot pile up
+            Singularity::Extend(probe.m)
+        } else if singular_beta >= beta {
+            Singularity::Cut(singular_beta)
+        } else {
+            Singularity::None
+        }
+    }
+
     fn mate_distance_pruning(&self, ply: i16, alpha: &mut Score, beta: &mut Score) -> Option<Score> {
-        let new_alpha = (*alpha).max(self.checkmate_score(ply));
-        let new_beta = (*beta).min(-self.checkmate_score(ply));
+        // even the quickest mate cannot be better than mating with the next move
+        let new_alpha = (*alpha).max(Score::mated_in(ply));
+        let new_beta = (*beta).min(Score::mate_in(ply + 1));
 
         if new_alpha >= new_beta {
             return Some(new_alpha)
@@ -294,97 +512,309 @@ impl<'a> Searcher<'a> {
         None
     }
 
-    pub fn go(&mut self, options: Options) -> Move {
+    pub fn go(&mut self, options: Options) -> BestMove {
+        self.transpositions.new_search();
+
         if let Some(book_move) = self.get_book_move() {
-            return book_move;
+            return BestMove { m: book_move, ponder: None };
         }
 
-        let target_depth = min(options.depth.unwrap_or(i16::MAX), MAX_DEPTH - 1);
-        self.start_time = SystemTime::now();
-        self.target_time = self.calculate_target_time(&options);
+        if options.threads <= 1 {
+            return self.search(options);
+        }
+
+        let helper_signals = Arc::new(Signals::new());
+        self.helper_signals = Some(helper_signals.clone());
+
+        thread::scope(|scope| {
+            for helper_id in 1..options.threads {
+                let board = self.board.clone();
+                let transpositions = self.transpositions;
+                let signals = helper_signals.clone();
+                let options = Options {
+                    target_time: None,
+                    nodes: None,
+                    mate: None,
+                    multi_pv: 1,
+                    infinite: true,
+                    ponder: false,
+                    ..options.clone()
+                };
+
+                thread::Builder::new()
+                    .name(format!("helper {}", helper_id))
+                    .stack_size(SEARCH_STACK_SIZE)
+                    .spawn_scoped(scope, move || {
+                        let mut helper = Searcher::new(board, transpositions, false, signals);
+                        helper.helper_id = helper_id;
+                        helper.search(options);
+                        helper.publish_nodes();
+                    })
+                    .expect("Cannot spawn helper thread");
+            }
+
+            let result = self.search(options);
+            helper_signals.stop();
+            result
+        })
+    }
+
+    fn skip_iteration(&self, depth: i16) -> bool {
+        if self.is_main() || depth == 1 {
+            return false;
+        }
+
+        let idx = (self.helper_id - 1) % SKIP_SIZE.len();
+        ((depth + SKIP_PHASE[idx]) / SKIP_SIZE[idx]) % 2 != 0
+    }
+
+    fn search(&mut self, options: Options) -> BestMove {
+        let skill = Skill::new(options.skill_level);
+        let mut target_depth = min(options.depth.unwrap_or(i16::MAX), MAX_DEPTH - 1);
+        if skill.enabled() {
+            target_depth = min(target_depth, skill.depth());
+        }
 
-        let mut eval = self.last_eval;
-        let mut abs_eval = 0;
+        self.start_time = SystemTime::now();
+        self.output = options.output;
+        self.show_wdl = options.show_wdl;
+        self.time_limits = self.calculate_time_limits(&options);
+        self.target_nodes = options.nodes.unwrap_or(u64::MAX);
+        self.multi_pv = max(options.multi_pv, 1);
+        let reported_lines = self.multi_pv;
+        if skill.enabled() {
+            self.multi_pv = max(self.multi_pv, skill::SKILL_MULTI_PV);
+        }
+        self.root_moves = self.get_moves(0, 0)
+            .into_iter()
+            .filter(|m| options.search_moves.is_empty() || options.search_moves.contains(m))
+            .map(RootMove::new)
+            .collect();
+
+        let mut eval = Score::ZERO;
+        let mut best_eval = Score::ZERO;
         let mut best_move = NULL_MOVE;
-        let mut pv = String::new();
+        let mut pv = Vec::new();
 
         let mut consecutive_evals = 0;
+        let mut stability = Stability::new();
         let mut last_turn = eval;
         let mut last_move = NULL_MOVE;
 
         for current_depth in 1..=target_depth {
-            let iter_start = SystemTime::now();
-            let last_eval = eval;
+            if self.skip_iteration(current_depth) {
+                continue;
+            }
 
             self.depth = current_depth;
             self.seldepth = 0;
 
-            let window_size = 40;
+            for root_move in &mut self.root_moves {
+                root_move.previous_score = root_move.score;
+            }
+
+            let lines = min(self.multi_pv, self.root_moves.len());
             let mut aspiration_fail = false;
 
-            eval = self.negamax(0, current_depth, last_eval - window_size, last_eval + window_size, true);
-            if self.time_hit {
-                break;
-            }
+            for pv_index in 0..lines {
+                self.pv_index = pv_index;
 
-            if (last_eval - eval).abs() >= window_size {
-                aspiration_fail = true;
-                eval = self.negamax(0, current_depth, Score::MIN + 1, Score::MAX, true);
+                // the window is centred on the score the line had in the previous iteration
+                let previous_score = self.root_moves[pv_index].previous_score;
+
+                let (line_eval, line_aspiration_fail) = self.aspiration_search(current_depth, previous_score);
                 if self.time_hit {
                     break;
                 }
+
+                aspiration_fail |= line_aspiration_fail;
+
+                if pv_index == 0 {
+                    eval = line_eval;
+                }
+            }
+
+            if self.time_hit {
+                break;
             }
 
-            best_move = self.best_move;
-            abs_eval = self.board.current_color.choose(eval, -eval);
-            pv = self.get_pv(current_depth);
+            best_move = self.root_moves.first().map_or(NULL_MOVE, |root_move| root_move.m);
+            best_eval = eval;
+
+            if !self.is_main() {
+                continue;
+            }
 
-            let time_taken = self.start_time.elapsed().unwrap();
-            let iter_taken = iter_start.elapsed().unwrap();
+            for line in 0..min(lines, reported_lines) {
+                let line_pv = self.get_line_pv(line);
+                let score = self.root_moves[line].score;
+                self.print_search_info(self.depth, line, score, Bound::Exact, &line_pv, aspiration_fail);
+                if line == 0 {
+                    pv = line_pv;
+                }
+            }
 
-            self.print_search_info(self.depth, abs_eval, pv.as_str(), aspiration_fail);
+            stability.best_move_changes /= 2.0;
+            match best_move == last_move {
+                true => stability.stable_iterations += 1,
+                false if last_move != NULL_MOVE => {
+                    stability.best_move_changes += 1.0;
+                    stability.stable_iterations = 0;
+                }
+                false => stability.stable_iterations = 0,
+            }
+            stability.best_move_effort = self.root_moves.first().map_or(0, |root_move| root_move.nodes) as f64 / max(self.nodes, 1) as f64;
+            stability.score_drop = match current_depth > 1 {
+                true => (i32::from(last_turn) - i32::from(best_eval)).max(0),
+                false => 0,
+            };
 
-            if time_taken.as_millis() >= self.target_time || iter_taken.as_millis() > self.target_time / 8 {
+            let soft_limit = self.time_limits.scaled_optimum(&stability);
+            if !self.signals.pondering() && self.start_time.elapsed().unwrap().as_millis() >= soft_limit {
                 break;
             }
 
-            if best_move == last_move && abs_eval == last_turn {
+            if let Some(mate) = options.mate {
+                if best_eval >= Score::mate_in(2 * mate - 1) {
+                    break;
+                }
+            }
+
+            if best_move == last_move && best_eval == last_turn {
                 consecutive_evals += 1;
             } else {
                 consecutive_evals = 0;
             }
 
-            let too_many_consecutive_checkmates = consecutive_evals > 8 && abs_eval.abs() >= self.checkmate_score(MAX_DEPTH).abs();
-            let too_many_consecutive_draws = consecutive_evals > 12 && abs_eval == 0;
+            let too_many_consecutive_checkmates = consecutive_evals > 8 && best_eval.is_mate();
+            let too_many_consecutive_draws = consecutive_evals > 12 && best_eval == Score::ZERO;
 
             if too_many_consecutive_checkmates || too_many_consecutive_draws {
                 break;
             }
 
             last_move = best_move;
-            last_turn = abs_eval;
+            last_turn = best_eval;
         }
 
-        if best_move == NULL_MOVE {
-            println!("info string null move selected as best, bug? overriding with a semi-random legal move");
+        if skill.enabled() && best_move != NULL_MOVE {
+            let lines = min(self.multi_pv, self.root_moves.len());
+            best_move = skill.pick_best(&self.root_moves[..lines]);
+        }
+
+        if best_move == NULL_MOVE && self.is_main() {
+            let uci = self.output == Output::Uci;
+            if uci {
+                output!("info string null move selected as best, bug? overriding with a semi-random legal move");
+            }
             let legal_moves = moves_generation::generate_all(&self.board);
             if !legal_moves.is_empty() {
                 best_move = legal_moves[0];
-            } else {
-                println!("info string no legal moves?!");
+            } else if uci {
+                output!("info string no legal moves?!");
+            }
+            if uci {
+                output!("info string current position is {}", self.board.export_fen());
             }
-            println!("info string current position is {}", self.board.export_fen());
         }
 
-        if self.time_hit {
-            self.print_search_info(self.depth - 1, abs_eval, pv.as_str(), false);
+        if self.time_hit && self.is_main() {
+            self.print_search_info(self.depth - 1, 0, best_eval, Bound::Exact, &pv, false);
+        }
+
+        let ponder = match best_move {
+            NULL_MOVE => None,
+            _ => self.ponder_move(best_move),
+        };
+        BestMove { m: best_move, ponder }
+    }
+
+    /// Searches the current line with a window around the previous score. Whenever the score
+    /// falls outside of the window, only the failing side is widened, twice as much on each retry.
+    fn aspiration_search(&mut self, depth: i16, previous_score: Score) -> (Score, bool) {
+        let (mut alpha, mut beta) = (Score::MIN, Score::INFINITE);
+        let mut delta = params::ASPIRATION_WINDOW.get() as i16;
+
+        if previous_score != UNKNOWN_SCORE {
+            alpha = previous_score - delta;
+            beta = previous_score + delta;
+        }
+
+        let mut failed = false;
+
+        loop {
+            let score = self.negamax(0, depth, alpha, beta, true);
+            if self.time_hit {
+                return (score, failed);
+            }
+
+            self.root_moves[self.pv_index..].sort_by_key(|root_move| Reverse(root_move.score));
+
+            let bound = match score {
+                score if score <= alpha && alpha > Score::MIN => Bound::Upper,
+                score if score >= beta && beta < Score::INFINITE => Bound::Lower,
+                _ => return (score, failed),
+            };
+
+            match bound {
+                Bound::Upper => alpha = score - delta,
+                _ => beta = score + delta,
+            }
+
+            failed = true;
+            delta = delta.saturating_mul(2);
+
+            if self.is_main() {
+                let line_pv = self.get_line_pv(self.pv_index);
+                self.print_search_info(depth, self.pv_index, score, bound, &line_pv, true);
+            }
+        }
+    }
+
+    fn get_root_moves(&self) -> MoveList {
+        let moves: Vec<Move> = self.root_moves[self.pv_index..].iter().map(|root_move| root_move.m).collect();
+        let weights = (0..moves.len() as i32).rev().collect();
+        MoveList::new(moves, weights)
+    }
+
+    fn update_root_move(&mut self, m: Move, score: Score, alpha: Score, first: bool, nodes: u64) {
+        let child_length = self.pv_length[1];
+        if let Some(root_move) = self.root_moves.iter_mut().find(|root_move| root_move.m == m) {
+            root_move.nodes += nodes;
+            root_move.score = match first || score > alpha {
+                true => score,
+                false => UNKNOWN_SCORE,
+            };
+            if first || score > alpha {
+                root_move.pv.truncate(0);
+                root_move.pv.push(m);
+                root_move.pv.extend_from_slice(&self.pv[1][..child_length]);
+            }
+        }
+    }
+
+    fn get_line_pv(&self, line: usize) -> Vec<Move> {
+        self.root_moves[line].pv.clone()
+    }
+
+    fn ponder_move(&mut self, best_move: Move) -> Option<Move> {
+        if let Some(root_move) = self.root_moves.iter().find(|root_move| root_move.m == best_move) {
+            if root_move.pv.len() > 1 {
+                return Some(root_move.pv[1]);
+            }
         }
 
-        self.last_eval = -eval;
-        best_move
+        // the line got cut short by a transposition table hit, the table might know the reply
+        self.board.make_move(best_move);
+        let reply = self.transpositions.get_move(self.board.key())
+            .filter(|m| moves_generation::generate_all(&self.board).contains(m));
+        self.board.unmake_move();
+        reply
     }
 
     fn negamax(&mut self, ply: i16, mut depth: i16, mut alpha: Score, mut beta: Score, root: bool) -> Score {
+        self.clear_pv(ply);
+
         if self.board.in_check() {
             depth += 1;
         }
@@ -393,7 +823,7 @@ impl<'a> Searcher<'a> {
             return self.qsearch(ply, 0, alpha, beta);
         }
 
-        if let Some(score) = self.break_conditions(depth, alpha, beta, root) {
+        if let Some(score) = self.break_conditions(ply, depth, alpha, beta, root) {
             return score;
         }
 
@@ -403,28 +833,48 @@ impl<'a> Searcher<'a> {
 
         self.nodes += 1;
         self.nodes_n += 1;
-        let moves = self.get_moves::<ALL_MOVES>(depth);
+        self.set_static_eval(ply, None);
+        let moves = self.get_moves(ply, depth);
 
         if let Some(score) = self.no_moves_conditions(ply, &moves) {
             return score;
         }
 
+        let singularity = match root {
+            true => Singularity::None,
+            false => self.singular_search(ply, depth, beta),
+        };
+
+        if let Singularity::Cut(score) = singularity {
+            return score;
+        }
+
         let mut best = NULL_MOVE;
         let mut found_exact = false;
         let mut move_counter = 0;
+        let mut quiets = Vec::new();
+
+        let moves = match root {
+            true => self.get_root_moves(),
+            false => moves,
+        };
 
         for m in moves {
+            let full_depth = depth - 1 + singularity.extension(m);
+            let nodes_before = self.nodes;
+
+            self.push_move(ply, m);
             self.board.make_move(m.clone());
 
             let score = match move_counter > 0 {
-                false => -self.negamax(ply + 1, depth - 1, -beta, -alpha, false),
+                false => -self.negamax(ply + 1, full_depth, -beta, -alpha, false),
                 true => {
-                    let mut next_depth = depth - 1;
+                    let mut next_depth = full_depth;
                     next_depth -= self.late_move_reduction(depth, m, move_counter);
 
                     let mut score = -self.zero_window(ply + 1, next_depth, -alpha, false);
                     if score > alpha {
-                        score = -self.negamax(ply + 1, depth - 1, -beta, -alpha, false);
+                        score = -self.negamax(ply + 1, full_depth, -beta, -alpha, false);
                     }
                     score
                 }
@@ -433,29 +883,43 @@ impl<'a> Searcher<'a> {
             self.board.unmake_move();
 
             if self.time_hit {
-                return 0;
+                return Score::ZERO;
+            }
+
+            if root {
+                self.update_root_move(m, score, alpha, move_counter == 0, self.nodes - nodes_before);
+            }
+
+            if score > alpha {
+                self.update_pv(ply, m);
             }
 
+            let store = !root || self.pv_index == 0;
+
             if score >= beta {
-                self.transpositions.set(self.board.key(), depth, TableScore::LowerBound(beta), m);
-                self.store_killer(depth, m);
+                if store {
+                    self.transpositions.set(self.board.key(), ply, depth, TableScore::LowerBound(beta), m, self.static_eval(ply));
+                }
+                self.update_quiet_stats(ply, depth, m, &quiets);
                 return beta;
             }
 
+            if self.is_quiet(m) {
+                quiets.push(m);
+            }
+
             if score > alpha {
                 best = m;
                 found_exact = true;
                 alpha = score;
-
-                if root {
-                    self.best_move = m;
-                }
             }
 
             move_counter += 1;
         }
 
-        self.transpositions.set(self.board.key(), depth, TableScore::from_alpha(alpha, found_exact), best);
+        if !root || self.pv_index == 0 {
+            self.transpositions.set(self.board.key(), ply, depth, TableScore::from_alpha(alpha, found_exact), best, self.static_eval(ply));
+        }
         self.seldepth = max(self.seldepth, self.depth - depth);
 
         alpha
@@ -470,18 +934,29 @@ impl<'a> Searcher<'a> {
             return self.qsearch(ply, 0, beta - 1, beta);
         }
 
-        if let Some(score) = self.break_conditions(depth, beta - 1, beta, false) {
-            return score;
+        // the verification of a singular move searches the very same position, which has passed
+        // these checks already, and whose table entry must not cut the search short
+        let excluded = self.excluded(ply);
+
+        if excluded.is_none() {
+            if let Some(score) = self.break_conditions(ply, depth, beta - 1, beta, false) {
+                return score;
+            }
         }
 
         if let Some(score) = self.mate_distance_pruning(ply, &mut (beta - 1), &mut beta) {
             return score;
         }
 
-        let current_eval = eval::evaluate(&self.board, Verbosity::Quiet) * self.board.side_to_move().choose(1, -1);
+        let current_eval = match self.transpositions.probe(self.board.key(), ply).and_then(|probe| probe.static_eval) {
+            Some(static_eval) => static_eval,
+            None => self.evaluate(),
+        };
+        self.set_static_eval(ply, Some(current_eval));
 
         // Razoring
-        if !self.board.in_check() && current_eval + 500 + 200 * depth * depth < beta - 1 {
+        let razoring_margin = params::RAZORING_MARGIN.get() + params::RAZORING_DEPTH_MARGIN.get() * depth as i32 * depth as i32;
+        if excluded.is_none() && !self.board.in_check() && i32::from(current_eval) + razoring_margin < i32::from(beta - 1) {
             self.razoring_attempts += 1;
             let quiescence_eval = self.qsearch(ply, 0, beta - 1, beta);
             if quiescence_eval < beta - 1 {
@@ -491,7 +966,7 @@ impl<'a> Searcher<'a> {
         }
 
         // Reverse futility pruning
-        if !last_null && !self.board.in_check() && depth < 3 {
+        if excluded.is_none() && !last_null && !self.board.in_check() && depth < 3 {
             let margin = match depth {
                 1 => weights::BASE_SCORES[Piece::Bishop],
                 2 => weights::BASE_SCORES[Piece::Rook],
@@ -504,11 +979,12 @@ impl<'a> Searcher<'a> {
         }
 
         // Null move pruning
-        if !last_null && !self.board.in_check() && self.board.any_piece.pieces() > 8 {
-            let null_reduction = 1 + depth * 2 / 3;
+        if excluded.is_none() && !last_null && !self.board.in_check() && self.board.any_piece.pieces() > 8 {
+            let null_reduction = (params::NULL_MOVE_REDUCTION.get() + depth as i32 * params::NULL_MOVE_DEPTH_PERCENT.get() / 100) as i16;
 
+            self.push_null(ply);
             self.board.make_null();
-            let value = -self.zero_window(ply + 2, depth - null_reduction, 1 - beta, true);
+            let value = -self.zero_window(ply + 2, depth - null_reduction, -beta + 1, true);
             self.board.unmake_null();
 
             if value >= beta {
@@ -518,12 +994,18 @@ impl<'a> Searcher<'a> {
 
         self.nodes += 1;
         self.nodes_z += 1;
-        let moves = self.get_moves::<ALL_MOVES>(depth);
+        let moves = self.get_moves(ply, depth);
 
         if let Some(score) = self.no_moves_conditions(ply, &moves) {
             return score;
         }
 
+        let singularity = self.singular_search(ply, depth, beta);
+
+        if let Singularity::Cut(score) = singularity {
+            return score;
+        }
+
         // Internal iterative deepening
         if depth > 4 && self.transpositions.get_move(self.board.key()).is_none() {
             depth -= 2;
@@ -534,21 +1016,40 @@ impl<'a> Searcher<'a> {
         }
 
         let mut move_counter = 0;
+        let mut quiets = Vec::new();
+        let see_pruning = depth <= params::SEE_PRUNING_DEPTH.get() as i16 && !self.board.in_check();
 
         for m in moves {
-            let mut next_depth = depth - 1;
+            if excluded == Some(m) {
+                continue;
+            }
+
+            // Quiet moves losing material
+            if see_pruning && move_counter > 0 && self.is_quiet(m) && !self.killers[Self::killer_index(depth)].contains(&m)
+                && moves_generation::see(&self.board, m) < -params::SEE_QUIET_MARGIN.get() * depth as i32 {
+                continue;
+            }
+
+            let mut next_depth = depth - 1 + singularity.extension(m);
             next_depth -= self.late_move_reduction(depth, m, move_counter);
 
+            self.push_move(ply, m);
             self.board.make_move(m);
-            let eval = -self.zero_window(ply + 1, next_depth, 1 - beta, false);
+            let eval = -self.zero_window(ply + 1, next_depth, -beta + 1, false);
             self.board.unmake_move();
 
             if eval >= beta {
-                self.transpositions.set(self.board.key(), depth, TableScore::LowerBound(beta), NULL_MOVE);
-                self.store_killer(depth, m);
+                if excluded.is_none() {
+                    self.transpositions.set(self.board.key(), ply, depth, TableScore::LowerBound(beta), m, self.static_eval(ply));
+                }
+                self.update_quiet_stats(ply, depth, m, &quiets);
                 return beta;
             }
 
+            if self.is_quiet(m) {
+                quiets.push(m);
+            }
+
             move_counter += 1;
         }
 
@@ -556,7 +1057,7 @@ impl<'a> Searcher<'a> {
     }
 
     fn qsearch(&mut self, ply: i16, depth: i16, mut alpha: Score, mut beta: Score) -> Score {
-        if let Some(score) = self.break_conditions(depth, alpha, beta, false) {
+        if let Some(score) = self.break_conditions(ply, depth, alpha, beta, false) {
             return score;
         }
 
@@ -564,48 +1065,63 @@ impl<'a> Searcher<'a> {
             return score;
         }
 
-        let side = self.board.side_to_move();
-        let multiplier = side.choose(1, -1);
-
         self.nodes += 1;
         self.nodes_q += 1;
 
-        if self.board.in_checkmate() {
-            return self.checkmate_score(depth);
-        }
+        // when in check there is no standing pat, all the evasions have to be searched
+        let in_check = self.board.in_check();
+        self.set_static_eval(ply, None);
 
-        let score = eval::evaluate(&self.board, Verbosity::Quiet) * multiplier;
+        if !in_check {
+            let score = self.evaluate();
+            self.set_static_eval(ply, Some(score));
 
-        let delta_margin = weights::BASE_SCORES[Piece::Queen];
+            let delta_margin = params::DELTA_MARGIN.get() as i16;
 
-        if score + delta_margin < alpha && !self.board.in_check() {
-            self.delta_prunes += 1;
-            return alpha;
-        }
+            if score + delta_margin < alpha {
+                self.delta_prunes += 1;
+                return alpha;
+            }
+
+            if score >= beta {
+                return beta;
+            }
 
-        if score >= beta {
-            return beta;
+            if score > alpha {
+                alpha = score;
+            }
         }
 
-        if score > alpha {
-            alpha = score;
+        let moves = match in_check {
+            true => moves_generation::generate_all(&self.board),
+            false => moves_generation::generate_tactical(&self.board, depth == 0),
+        };
+
+        if in_check && moves.is_empty() {
+            return Score::mated_in(ply);
         }
 
-        let moves = self.get_moves::<CAPTURES_ONLY>(depth);
+        let moves = self.order_moves(moves, ply, depth);
         let mut best = NULL_MOVE;
         let mut found_exact = false;
 
         for capture in moves {
+            // Losing captures
+            if !in_check && moves_generation::see(&self.board, capture) < 0 {
+                continue;
+            }
+
+            self.push_move(ply, capture);
             self.board.make_move(capture);
             let score = -self.qsearch(ply + 1, depth - 1, -beta, -alpha);
             self.board.unmake_move();
 
             if self.time_hit {
-                return 0;
+                return Score::ZERO;
             }
 
             if score >= beta {
-                self.transpositions.set(self.board.key(), depth, TableScore::LowerBound(beta), capture);
+                self.transpositions.set(self.board.key(), ply, depth, TableScore::LowerBound(beta), capture, self.static_eval(ply));
                 return beta;
             }
 
@@ -617,7 +1133,7 @@ impl<'a> Searcher<'a> {
         }
 
         if best != NULL_MOVE {
-            self.transpositions.set(self.board.key(), depth, TableScore::from_alpha(alpha, found_exact), best);
+            self.transpositions.set(self.board.key(), ply, depth, TableScore::from_alpha(alpha, found_exact), best, self.static_eval(ply));
             self.seldepth = max(self.seldepth, self.depth - depth);
         }
 
@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+pub struct Signals {
+    stop: AtomicBool,
+    ponder: AtomicBool,
+    nodes: AtomicU64,
+}
+
+impl Signals {
+    pub fn new() -> Self {
+        Self {
+            stop: AtomicBool::new(false),
+            ponder: AtomicBool::new(false),
+            nodes: AtomicU64::new(0),
+        }
+    }
+
+    pub fn reset(&self, ponder: bool) {
+        self.stop.store(false, Ordering::Relaxed);
+        self.ponder.store(ponder, Ordering::Relaxed);
+        self.nodes.store(0, Ordering::Relaxed);
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// The opponent played the expected move, so the search should become a regular timed one
+    pub fn ponderhit(&self) {
+        self.ponder.store(false, Ordering::Relaxed);
+    }
+
+    pub fn pondering(&self) -> bool {
+        self.ponder.load(Ordering::Relaxed)
+    }
+
+    pub fn add_nodes(&self, nodes: u64) {
+        self.nodes.fetch_add(nodes, Ordering::Relaxed);
+    }
+
+    /// Nodes searched by all threads which report to this set of signals
+    pub fn nodes(&self) -> u64 {
+        self.nodes.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,60 @@
+use rand::Rng;
+use crate::search::root_move::{RootMove, UNKNOWN_SCORE};
+use crate::search::{weights, Score};
+use crate::types::{Move, Piece};
+
+pub const MAX_SKILL_LEVEL: f64 = 20.0;
+pub const MIN_ELO: i32 = 1000;
+pub const MAX_ELO: i32 = 2500;
+
+/// Number of root moves considered when the strength is limited
+pub const SKILL_MULTI_PV: usize = 4;
+
+pub fn level_from_elo(elo: i32) -> f64 {
+    let elo = elo.clamp(MIN_ELO, MAX_ELO);
+    MAX_SKILL_LEVEL * (elo - MIN_ELO) as f64 / (MAX_ELO - MIN_ELO) as f64
+}
+
+pub struct Skill {
+    level: f64,
+}
+
+impl Skill {
+    pub fn new(level: f64) -> Self {
+        Self { level: level.clamp(0.0, MAX_SKILL_LEVEL) }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.level < MAX_SKILL_LEVEL
+    }
+
+    pub fn depth(&self) -> i16 {
+        1 + self.level as i16
+    }
+
+    /// Choose one of the best root moves. Every move gets a random bonus, scaled by how weak we want to be
+    /// and by the spread of the scores, so moves that are only slightly worse get picked much more often
+    /// than clear mistakes.
+    pub fn pick_best(&self, root_moves: &[RootMove]) -> Move {
+        let top_score = i32::from(root_moves[0].score);
+        let last_score = i32::from(root_moves[root_moves.len() - 1].score);
+        let delta = (top_score - last_score).min(weights::BASE_SCORES[Piece::Pawn] as i32);
+        let weakness = 120 - (2.0 * self.level) as i32;
+
+        let mut rng = rand::thread_rng();
+        let mut best = root_moves[0].m;
+        let mut max_score = i32::from(Score::MIN);
+
+        for root_move in root_moves.iter().filter(|root_move| root_move.score != UNKNOWN_SCORE) {
+            let score = i32::from(root_move.score);
+            let push = (weakness * (top_score - score) + delta * rng.gen_range(0..weakness)) / 128;
+
+            if score + push >= max_score {
+                max_score = score + push;
+                best = root_move.m;
+            }
+        }
+
+        best
+    }
+}
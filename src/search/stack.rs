@@ -0,0 +1,25 @@
+use crate::search::Score;
+use crate::types::{Move, Piece, Side};
+
+/// What happened at a single ply of the current search line
+#[derive(Clone, Copy)]
+pub struct StackEntry {
+    pub m: Move,
+    pub piece: Option<Piece>,
+    pub side: Side,
+    pub static_eval: Option<Score>,
+    // move skipped while verifying whether it is singular
+    pub excluded: Option<Move>,
+}
+
+impl StackEntry {
+    pub const fn new() -> Self {
+        Self {
+            m: Move::new(),
+            piece: None,
+            side: Side::White,
+            static_eval: None,
+            excluded: None,
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::cmp::{max, min};
+use crate::search::Options;
+use crate::types::Side;
+
+pub const DEFAULT_MOVE_OVERHEAD: i32 = 30;
+pub const MAX_MOVE_OVERHEAD: i32 = 5000;
+
+// without movestogo the remaining time is assumed to be spread across this many moves
+const DEFAULT_MOVES_TO_GO: i64 = 30;
+// a single move may take at most this many times the optimum time...
+const MAXIMUM_RATIO: i64 = 5;
+// ...but never more than this per mille of the remaining time, unless it is the last move before
+// the time control
+const MAXIMUM_SHARE: i64 = 800;
+
+/// How settled the search is after the last iteration
+pub struct Stability {
+    // decays by half every iteration, so that only the recent changes matter
+    pub best_move_changes: f64,
+    pub stable_iterations: usize,
+    // share of the root nodes spent on the best move
+    pub best_move_effort: f64,
+    // how much the score got worse since the previous iteration
+    pub score_drop: i32,
+}
+
+impl Stability {
+    pub fn new() -> Self {
+        Self { best_move_changes: 0.0, stable_iterations: 0, best_move_effort: 0.0, score_drop: 0 }
+    }
+}
+
+/// Time to spend on a move, in milliseconds: the search does not start a new iteration after the
+/// optimum time has passed, and it is interrupted once it reaches the maximum time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeLimits {
+    pub optimum: u128,
+    pub maximum: u128,
+}
+
+impl TimeLimits {
+    pub const INFINITE: TimeLimits = TimeLimits { optimum: u128::MAX, maximum: u128::MAX };
+
+    pub fn new(options: &Options, side: Side) -> Self {
+        if options.infinite {
+            return Self::INFINITE;
+        }
+
+        let overhead = options.move_overhead as i64;
+
+        if let Some(move_time) = options.target_time {
+            let time = max(move_time as i64 - overhead, 1) as u128;
+            return Self { optimum: time, maximum: time };
+        }
+
+        let time = side.choose(options.white_time, options.black_time) as i64;
+        let increment = side.choose(options.white_increment, options.black_increment) as i64;
+        let moves_to_go = options.moves_to_go.map_or(DEFAULT_MOVES_TO_GO, |moves| max(moves, 1) as i64);
+
+        let safe_time = max(time - overhead, 1);
+        let total_time = max(time + increment * (moves_to_go - 1) - overhead * moves_to_go, 1);
+        let optimum = min(total_time / moves_to_go, safe_time);
+        let maximum = match moves_to_go {
+            1 => safe_time,
+            _ => min(optimum * MAXIMUM_RATIO, safe_time * MAXIMUM_SHARE / 1000),
+        };
+
+        Self { optimum: optimum as u128, maximum: max(optimum, maximum) as u128 }
+    }
+
+    /// The optimum time shortened when the best move keeps winning most of the nodes over several
+    /// iterations, and extended when the best move changes or the score drops
+    pub fn scaled_optimum(&self, stability: &Stability) -> u128 {
+        if self.optimum == u128::MAX {
+            return u128::MAX;
+        }
+
+        let changes = 1.0 + 1.5 * stability.best_move_changes;
+        let stable = match stability.stable_iterations {
+            0..=2 => 1.0,
+            3..=5 => 0.85,
+            _ => 0.7,
+        };
+        let effort = (1.5 - stability.best_move_effort).clamp(0.6, 1.2);
+        let drop = (1.0 + stability.score_drop as f64 / 100.0).clamp(1.0, 2.0);
+
+        let scaled = (self.optimum as f64 * changes * stable * effort * drop) as u128;
+        min(scaled, self.maximum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits() {
+        let mut options = Options::new();
+        options.target_time = Some(50);
+        assert_eq!(TimeLimits::new(&options, Side::White), TimeLimits { optimum: 20, maximum: 20 });
+        options.target_time = Some(10);
+        assert_eq!(TimeLimits::new(&options, Side::White), TimeLimits { optimum: 1, maximum: 1 });
+
+        let mut options = Options::new();
+        options.white_time = 60000;
+        options.black_time = 1000;
+        let white = TimeLimits::new(&options, Side::White);
+        let black = TimeLimits::new(&options, Side::Black);
+        assert!(white.optimum < white.maximum && white.maximum < 60000);
+        assert!(black.optimum < white.optimum && black.maximum < 1000);
+
+        options.moves_to_go = Some(1);
+        let last_move = TimeLimits::new(&options, Side::White);
+        assert_eq!(last_move, TimeLimits { optimum: 59970, maximum: 59970 });
+    }
+
+    #[test]
+    fn scaling() {
+        let limits = TimeLimits { optimum: 1000, maximum: 5000 };
+
+        let mut stability = Stability::new();
+        stability.stable_iterations = 8;
+        stability.best_move_effort = 0.95;
+        assert!(limits.scaled_optimum(&stability) < 1000);
+
+        let mut stability = Stability::new();
+        stability.best_move_changes = 1.5;
+        stability.best_move_effort = 0.4;
+        stability.score_drop = 80;
+        assert_eq!(limits.scaled_optimum(&stability), 5000);
+    }
+}
@@ -0,0 +1,262 @@
+use std::fs;
+use crate::board::{Board, FenConsumer};
+use crate::search::eval::Eval;
+use crate::types::Side;
+
+// The win rate for a score x is modelled as 1 / (1 + e^((a - x) / b)), where a and b are cubic
+// polynomials of the amount of material left on the board. The loss rate is the win rate of -x,
+// whatever remains is the draw rate.
+
+const MIN_MATERIAL: i32 = 17;
+const MAX_MATERIAL: i32 = 78;
+const REFERENCE_MATERIAL: f64 = 58.0;
+// material counts with fewer positions than this are left out of the fit
+const MIN_GROUP_SIZE: usize = 100;
+
+// fitted to 234817 positions from 2000 self-play games at 10000 nodes per move, generated and
+// fitted with:
+//     python3 tools/selfplay.py target/release/kopyto 2000 20261017 selfplay.txt
+//     target/release/kopyto fitwdl selfplay.txt
+// the games depend on the search, so they have to be played again whenever it changes
+pub const DEFAULT_MODEL: WdlModel = WdlModel {
+    a: [-666.735, 1873.415, -1697.259, 557.258],
+    b: [-364.553, 1049.992, -893.466, 313.746],
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WdlModel {
+    pub a: [f64; 4],
+    pub b: [f64; 4],
+}
+
+pub struct Wdl {
+    pub win: i32,
+    pub draw: i32,
+    pub loss: i32,
+}
+
+/// Material count with the classic 1/3/3/5/9 piece values, used as the game phase input
+pub fn material(board: &Board) -> i32 {
+    [Side::White, Side::Black]
+        .iter()
+        .map(|side| {
+            board.pawns[*side].pieces()
+                + 3 * board.knights[*side].pieces()
+                + 3 * board.bishops[*side].pieces()
+                + 5 * board.rooks[*side].pieces()
+                + 9 * board.queens[*side].pieces()
+        })
+        .sum::<u32>() as i32
+}
+
+fn polynomial(coefficients: &[f64; 4], m: f64) -> f64 {
+    ((coefficients[0] * m + coefficients[1]) * m + coefficients[2]) * m + coefficients[3]
+}
+
+// the win and loss rates for the given parameters of the model
+fn rates(a: f64, b: f64, score: f64) -> (f64, f64) {
+    let win = 1.0 / (1.0 + ((a - score) / b).exp());
+    let loss = 1.0 / (1.0 + ((a + score) / b).exp());
+    (win, loss)
+}
+
+fn log_probability(a: f64, b: f64, sample: &Sample) -> f64 {
+    let (win, loss) = rates(a, b, sample.score);
+    let probability = match sample.result {
+        r if r > 0.75 => win,
+        r if r < 0.25 => loss,
+        _ => 1.0 - win - loss,
+    };
+    probability.max(1e-9).ln()
+}
+
+fn log_likelihood(a: f64, b: f64, samples: &[&Sample]) -> f64 {
+    samples.iter().map(|sample| log_probability(a, b, sample)).sum::<f64>() / samples.len() as f64
+}
+
+impl WdlModel {
+    fn parameters(&self, material: i32) -> (f64, f64) {
+        let m = material.clamp(MIN_MATERIAL, MAX_MATERIAL) as f64 / REFERENCE_MATERIAL;
+        (polynomial(&self.a, m), polynomial(&self.b, m))
+    }
+
+    /// Win/draw/loss chances in per mille for the given side-relative score
+    pub fn wdl(&self, score: Eval, material: i32) -> Wdl {
+        let (a, b) = self.parameters(material);
+        let (win, loss) = rates(a, b, score as f64);
+        let win = (1000.0 * win).round() as i32;
+        let loss = (1000.0 * loss).round() as i32;
+        Wdl { win, draw: 1000 - win - loss, loss }
+    }
+
+    /// Finds the parameters maximizing the likelihood of the game results in the given file.
+    /// Every line holds a position, its score from the white point of view, and the game result
+    /// (1-0, 1/2-1/2 or 0-1), separated with semicolons.
+    pub fn fit(path: &str) -> Result<Self, String> {
+        let samples = read_samples(path)?;
+
+        // a and b are fitted for every material count on its own first and the polynomials are
+        // fitted through them; the coefficients depend on each other too much to search them directly
+        let mut points = Vec::new();
+        for material in MIN_MATERIAL..=MAX_MATERIAL {
+            let group: Vec<&Sample> = samples
+                .iter()
+                .filter(|sample| sample.material.clamp(MIN_MATERIAL, MAX_MATERIAL) == material)
+                .collect();
+            if group.len() < MIN_GROUP_SIZE {
+                continue;
+            }
+            let (a, b) = fit_parameters(&group, (0.0, 100.0));
+            points.push(Point { m: material as f64 / REFERENCE_MATERIAL, a, b, weight: group.len() as f64 });
+        }
+
+        if points.len() < 4 {
+            return Err(String::from("not enough positions to fit the model to"));
+        }
+
+        let model = Self {
+            a: fit_polynomial(&points, |point| point.a),
+            b: fit_polynomial(&points, |point| point.b),
+        };
+
+        let likelihood = samples.iter().map(|sample| {
+            let (a, b) = model.parameters(sample.material);
+            log_probability(a, b, sample)
+        }).sum::<f64>() / samples.len() as f64;
+        output!("positions: {} log likelihood: {:.6}", samples.len(), likelihood);
+        Ok(model)
+    }
+}
+
+struct Point {
+    m: f64,
+    a: f64,
+    b: f64,
+    weight: f64,
+}
+
+/// Coordinate ascent over a and b, shrinking the step whenever neither of them can be improved
+fn fit_parameters(samples: &[&Sample], (mut a, mut b): (f64, f64)) -> (f64, f64) {
+    let mut likelihood = log_likelihood(a, b, samples);
+    let mut step = 16.0;
+
+    while step > 1e-3 {
+        let candidates = [(a + step, b), (a - step, b), (a, b + step), (a, b - step)];
+        match candidates
+            .into_iter()
+            .filter(|(_, b)| *b > 0.0)
+            .map(|(a, b)| (a, b, log_likelihood(a, b, samples)))
+            .find(|(_, _, candidate_likelihood)| *candidate_likelihood > likelihood) {
+            Some((new_a, new_b, new_likelihood)) => {
+                (a, b, likelihood) = (new_a, new_b, new_likelihood);
+            }
+            None => step /= 2.0,
+        }
+    }
+
+    (a, b)
+}
+
+/// Weighted least squares fit of a cubic polynomial, solving the normal equations
+fn fit_polynomial(points: &[Point], value: impl Fn(&Point) -> f64) -> [f64; 4] {
+    // the coefficients go from the highest power down, as in polynomial()
+    let mut matrix = [[0.0; 5]; 4];
+    for point in points {
+        let powers = [point.m.powi(3), point.m.powi(2), point.m, 1.0];
+        for row in 0..4 {
+            for column in 0..4 {
+                matrix[row][column] += point.weight * powers[row] * powers[column];
+            }
+            matrix[row][4] += point.weight * powers[row] * value(point);
+        }
+    }
+
+    for column in 0..4 {
+        let pivot = (column..4)
+            .max_by(|&x, &y| matrix[x][column].abs().total_cmp(&matrix[y][column].abs()))
+            .unwrap();
+        matrix.swap(column, pivot);
+        let pivot_row = matrix[column];
+        for row in (0..4).filter(|&row| row != column) {
+            let factor = matrix[row][column] / pivot_row[column];
+            for (value, pivot_value) in matrix[row].iter_mut().zip(pivot_row).skip(column) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+
+    std::array::from_fn(|idx| matrix[idx][4] / matrix[idx][idx])
+}
+
+struct Sample {
+    score: f64,
+    material: i32,
+    result: f64,
+}
+
+fn parse_sample(line: &str) -> Result<Sample, String> {
+    let fields: Vec<&str> = line.split(';').map(|field| field.trim()).collect();
+    if fields.len() != 3 {
+        return Err(format!("expected fen;score;result, got: {}", line));
+    }
+    let board = Board::from_fen(fields[0])?;
+    let score = fields[1].parse::<f64>().map_err(|_| format!("invalid score: {}", fields[1]))?;
+    let result = match fields[2] {
+        "1-0" => 1.0,
+        "1/2-1/2" => 0.5,
+        "0-1" => 0.0,
+        result => return Err(format!("invalid result: {}", result)),
+    };
+
+    // the model works with side-relative scores and results
+    Ok(match board.side_to_move() {
+        Side::White => Sample { score, material: material(&board), result },
+        Side::Black => Sample { score: -score, material: material(&board), result: 1.0 - result },
+    })
+}
+
+fn read_samples(path: &str) -> Result<Vec<Sample>, String> {
+    fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {}", path, e))?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_sample)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symmetric() {
+        for material in [MIN_MATERIAL, 40, MAX_MATERIAL] {
+            let even = DEFAULT_MODEL.wdl(0, material);
+            assert_eq!(even.win, even.loss);
+            assert!(even.draw > 0);
+
+            let ahead = DEFAULT_MODEL.wdl(150, material);
+            let behind = DEFAULT_MODEL.wdl(-150, material);
+            assert_eq!(ahead.win, behind.loss);
+            assert_eq!(ahead.win + ahead.draw + ahead.loss, 1000);
+            assert!(ahead.win > even.win);
+        }
+    }
+
+    #[test]
+    fn polynomial_fit() {
+        let coefficients = [-20.0, 40.0, -10.0, 40.0];
+        let points: Vec<Point> = (MIN_MATERIAL..=MAX_MATERIAL)
+            .step_by(5)
+            .map(|material| {
+                let m = material as f64 / REFERENCE_MATERIAL;
+                Point { m, a: polynomial(&coefficients, m), b: 0.0, weight: material as f64 }
+            })
+            .collect();
+
+        let fitted = fit_polynomial(&points, |point| point.a);
+        for (fitted, expected) in fitted.iter().zip(coefficients) {
+            assert!((fitted - expected).abs() < 1e-6);
+        }
+    }
+}
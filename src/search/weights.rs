@@ -1,10 +1,10 @@
 use std::ops::Index;
-use crate::search::Score;
+use crate::search::eval::Eval;
 use crate::types::Piece;
 
-pub type PieceTable = [Score; 5];
+pub type PieceTable = [Eval; 5];
 
-const fn acquire(pt: &PieceTable, piece: Piece) -> &Score {
+const fn acquire(pt: &PieceTable, piece: Piece) -> &Eval {
     match piece {
         Piece::King => &0,
         Piece::Pawn => &pt[0],
@@ -16,7 +16,7 @@ const fn acquire(pt: &PieceTable, piece: Piece) -> &Score {
 }
 
 impl Index<Piece> for PieceTable {
-    type Output = Score;
+    type Output = Eval;
 
     fn index(&self, index: Piece) -> &Self::Output {
         acquire(self, index)
@@ -26,14 +26,14 @@ impl Index<Piece> for PieceTable {
 pub const BASE_SCORES: PieceTable = [50, 300, 320, 500, 900];
 pub const END_SCORES: PieceTable = [80, 300, 320, 500, 900];
 
-pub const SIDE_STARTING_MATERIAL: Score =
+pub const SIDE_STARTING_MATERIAL: Eval =
     *acquire(&BASE_SCORES, Piece::Knight) * 2 +
     *acquire(&BASE_SCORES, Piece::Bishop) * 2 +
     *acquire(&BASE_SCORES, Piece::Rook) * 2 +
     *acquire(&BASE_SCORES, Piece::Queen);
 
-pub type HalfWeights = [Score; 32];
-pub type Weights = [Score; 64];
+pub type HalfWeights = [Eval; 32];
+pub type Weights = [Eval; 64];
 pub type WeightsPerSide = [Weights; 2];
 
 pub struct WeightSet {
@@ -1,90 +1,326 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 use crate::search::Score;
 use crate::types::Move;
 use crate::transpositions::TableScore;
 
+const CLUSTER_SIZE: usize = 4;
+
+// the lowest two bits of the flags hold the bound, the remaining ones the generation
+const BOUND_MASK: u8 = 0b11;
+const GENERATION_DELTA: u8 = 0b100;
+const GENERATION_MASK: u8 = !BOUND_MASK;
+
+const BOUND_NONE: u8 = 0;
+const BOUND_EXACT: u8 = 1;
+const BOUND_LOWER: u8 = 2;
+const BOUND_UPPER: u8 = 3;
+
+const NO_STATIC_EVAL: Score = Score::MIN;
+
+// a stale entry is worth this many plies of depth less for each search it has missed
+const AGE_PENALTY: i32 = 8;
+
+/// Unpacked contents of a table slot
 #[derive(Clone, Copy)]
 struct Entry {
-    hash: u64,
-    depth: i16,
-    score: TableScore,
     m: Move,
+    score: Score,
+    static_eval: Score,
+    depth: i8,
+    flags: u8,
 }
 
 impl Entry {
-    fn new() -> Self {
+    const fn new() -> Self {
         Self {
-            hash: 0,
-            depth: 0,
-            score: TableScore::Exact(0),
             m: Move::new(),
+            score: Score::ZERO,
+            static_eval: NO_STATIC_EVAL,
+            depth: i8::MIN,
+            flags: BOUND_NONE,
+        }
+    }
+
+    fn pack(&self) -> u64 {
+        u16::from(self.m) as u64
+            | (i32::from(self.score) as u16 as u64) << 16
+            | (i32::from(self.static_eval) as u16 as u64) << 32
+            | (self.depth as u8 as u64) << 48
+            | (self.flags as u64) << 56
+    }
+
+    fn unpack(data: u64) -> Self {
+        Self {
+            m: Move::from(data as u16),
+            score: Score::new((data >> 16) as u16 as i16),
+            static_eval: Score::new((data >> 32) as u16 as i16),
+            depth: (data >> 48) as u8 as i8,
+            flags: (data >> 56) as u8,
         }
     }
+
+    fn is_empty(&self) -> bool {
+        self.flags & BOUND_MASK == BOUND_NONE
+    }
+
+    fn generation(&self) -> u8 {
+        self.flags & GENERATION_MASK
+    }
+
+    fn age(&self, generation: u8) -> i32 {
+        (generation.wrapping_sub(self.generation()) / GENERATION_DELTA) as i32
+    }
+
+    fn table_score(&self, ply: i16) -> TableScore {
+        let score = self.score.to_search(ply);
+        match self.flags & BOUND_MASK {
+            BOUND_LOWER => TableScore::LowerBound(score),
+            BOUND_UPPER => TableScore::UpperBound(score),
+            _ => TableScore::Exact(score),
+        }
+    }
+}
+
+/// A slot holds the packed entry and the hash XOR-ed with it. Threads write both words without
+/// any locking, so the words may come from two different writes; the hash recovered from such
+/// a torn pair does not match, and the slot is then treated as belonging to another position.
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Slot {
+    fn new() -> Self {
+        Self { key: AtomicU64::new(0), data: AtomicU64::new(Entry::new().pack()) }
+    }
+
+    fn load(&self) -> (u64, Entry) {
+        let key = self.key.load(Ordering::Relaxed);
+        let data = self.data.load(Ordering::Relaxed);
+        (key ^ data, Entry::unpack(data))
+    }
+
+    fn store(&self, hash: u64, entry: &Entry) {
+        let data = entry.pack();
+        self.key.store(hash ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+}
+
+/// Slots sharing a single cache line
+#[repr(align(64))]
+struct Cluster([Slot; CLUSTER_SIZE]);
+
+/// Everything the table knows about a position
+#[derive(Clone, Copy)]
+pub struct Probe {
+    pub depth: i16,
+    pub score: TableScore,
+    pub m: Move,
+    pub static_eval: Option<Score>,
 }
 
+/// Lock-free table, shared between the search threads through an `Arc`
 pub struct Transpositions {
-    length: usize,
-    scores: Box<[Entry]>,
+    clusters: Box<[Cluster]>,
+    generation: AtomicU8,
 }
 
 impl Transpositions {
     pub fn new(desired_size: usize) -> Self {
-        let length = desired_size * 1048576 / size_of::<Entry>();
+        let length = (desired_size * 1048576 / size_of::<Cluster>()).max(1);
         Self {
-            length,
-            scores: vec![Entry::new(); length].into_boxed_slice(),
+            clusters: (0..length).map(|_| Cluster(std::array::from_fn(|_| Slot::new()))).collect(),
+            generation: AtomicU8::new(0),
         }
     }
 
+    /// Starts a new search, so that the entries stored by the previous ones get replaced sooner
+    pub fn new_search(&self) {
+        self.generation.fetch_add(GENERATION_DELTA, Ordering::Relaxed);
+    }
+
+    fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    // the high bits of the product select the cluster, so the table size is not limited to powers of two
+    fn cluster(&self, hash: u64) -> &Cluster {
+        &self.clusters[((hash as u128 * self.clusters.len() as u128) >> 64) as usize]
+    }
+
+    fn entry(&self, hash: u64) -> Option<Entry> {
+        self.cluster(hash).0
+            .iter()
+            .map(Slot::load)
+            .find(|(key, entry)| *key == hash && !entry.is_empty())
+            .map(|(_, entry)| entry)
+    }
+
     pub fn usage(&self) -> usize {
-        let elems = self.scores.iter().filter(|e| e.hash != 0).count();
-        elems * 1000 / self.length
+        let generation = self.generation();
+        let sample = self.clusters.len().min(1000);
+        let elems = self.clusters[..sample]
+            .iter()
+            .flat_map(|cluster| cluster.0.iter().map(Slot::load))
+            .filter(|(_, entry)| !entry.is_empty() && entry.generation() == generation)
+            .count();
+        elems * 1000 / (sample * CLUSTER_SIZE)
+    }
+
+    /// Scores are given relative to the root, the ply of the position is needed to convert mates
+    pub fn probe(&self, hash: u64, ply: i16) -> Option<Probe> {
+        self.entry(hash).map(|entry| Probe {
+            depth: entry.depth as i16,
+            score: entry.table_score(ply),
+            m: entry.m,
+            static_eval: match entry.static_eval {
+                NO_STATIC_EVAL => None,
+                static_eval => Some(static_eval),
+            },
+        })
     }
 
     pub fn get_move(&self, hash: u64) -> Option<Move> {
-        let entry = self.scores[hash as usize % self.length];
-        match entry.hash == hash {
-            true => Some(entry.m),
-            false => None,
-        }
+        self.entry(hash).map(|entry| entry.m).filter(|m| *m != Move::new())
     }
 
-    pub fn get(&self, hash: u64, depth: i16, alpha: Score, beta: Score) -> Option<Score> {
-        let entry = self.scores[hash as usize % self.length];
-        if entry.depth < depth || entry.hash != hash {
+    pub fn get(&self, hash: u64, ply: i16, depth: i16, alpha: Score, beta: Score) -> Option<Score> {
+        let entry = self.entry(hash)?;
+        if (entry.depth as i16) < depth {
             return None;
         }
 
-        match entry.score {
+        match entry.table_score(ply) {
             TableScore::Exact(score) => Some(score),
-            TableScore::LowerBound(score) if score <= alpha => Some(score),
-            TableScore::UpperBound(score) if score >= beta => Some(score),
+            TableScore::LowerBound(score) if score >= beta => Some(score),
+            TableScore::UpperBound(score) if score <= alpha => Some(score),
             _ => None,
         }
     }
 
-    pub fn set(&mut self, hash: u64, depth: i16, score: TableScore, m: Move) {
-        let idx = hash as usize % self.length;
-        if self.scores[idx].hash != hash || self.scores[idx].depth <= depth {
-            if match score {
-                TableScore::Exact(_) => true,
-                TableScore::LowerBound(score) => match self.scores[idx].score {
-                    TableScore::Exact(_) => false,
-                    TableScore::UpperBound(_) => true,
-                    TableScore::LowerBound(old_score) => score < old_score,
-                },
-                TableScore::UpperBound(score) => match self.scores[idx].score {
-                    TableScore::Exact(_) => false,
-                    TableScore::LowerBound(_) => true,
-                    TableScore::UpperBound(old_score) => score > old_score,
-                },
-            } {
-                self.scores[hash as usize % self.length] = Entry {
-                    hash,
-                    depth,
-                    score,
-                    m,
+    pub fn set(&self, hash: u64, ply: i16, depth: i16, score: TableScore, m: Move, static_eval: Option<Score>) {
+        let generation = self.generation();
+        let depth = depth.clamp(i8::MIN as i16 + 1, i8::MAX as i16) as i8;
+        let slots: [(u64, Entry); CLUSTER_SIZE] = std::array::from_fn(|idx| self.cluster(hash).0[idx].load());
+
+        // the entry of the same position if there is one, otherwise the shallowest and oldest one
+        let idx = slots.iter()
+            .position(|(key, entry)| *key == hash && !entry.is_empty())
+            .unwrap_or_else(|| (0..CLUSTER_SIZE)
+                .min_by_key(|&idx| slots[idx].1.depth as i32 - AGE_PENALTY * slots[idx].1.age(generation))
+                .unwrap());
+        let (key, entry) = slots[idx];
+
+        let same_position = key == hash && !entry.is_empty();
+        let exact = matches!(score, TableScore::Exact(_));
+        if same_position && !exact && entry.generation() == generation && depth < entry.depth.saturating_sub(3) {
+            return;
+        }
+
+        let (score, bound) = match score {
+            TableScore::Exact(score) => (score.to_table(ply), BOUND_EXACT),
+            TableScore::LowerBound(score) => (score.to_table(ply), BOUND_LOWER),
+            TableScore::UpperBound(score) => (score.to_table(ply), BOUND_UPPER),
+        };
+
+        self.cluster(hash).0[idx].store(hash, &Entry {
+            // a search which has not found any move should not erase the one known before
+            m: match (same_position, m == Move::new()) {
+                (true, true) => entry.m,
+                _ => m,
+            },
+            score,
+            static_eval: static_eval.unwrap_or(NO_STATIC_EVAL),
+            depth,
+            flags: generation | bound,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use super::*;
+
+    #[test]
+    fn layout() {
+        assert_eq!(size_of::<Slot>(), 16);
+        assert_eq!(size_of::<Cluster>(), 64);
+    }
+
+    #[test]
+    fn replacement() {
+        let transpositions = Transpositions::new(1);
+        let m = Move::from_uci("e2e4").unwrap();
+        transpositions.set(42, 0, 5, TableScore::Exact(Score::new(17)), m, Some(Score::new(3)));
+
+        let probe = transpositions.probe(42, 0).unwrap();
+        assert_eq!(probe.depth, 5);
+        assert_eq!(probe.m, m);
+        assert_eq!(probe.static_eval, Some(Score::new(3)));
+        assert!(matches!(probe.score, TableScore::Exact(score) if score == Score::new(17)));
+
+        // a shallower bound of the same search does not overwrite the deep entry...
+        transpositions.set(42, 0, 1, TableScore::LowerBound(Score::new(50)), Move::new(), None);
+        assert_eq!(transpositions.probe(42, 0).unwrap().depth, 5);
+
+        // ...but it does once a new search has started, keeping the move
+        transpositions.new_search();
+        transpositions.set(42, 0, 1, TableScore::LowerBound(Score::new(50)), Move::new(), None);
+        assert_eq!(transpositions.probe(42, 0).unwrap().depth, 1);
+        assert_eq!(transpositions.get_move(42), Some(m));
+        assert!(transpositions.probe(43, 0).is_none());
+    }
+
+    #[test]
+    fn torn_writes() {
+        let transpositions = Transpositions::new(1);
+        let first = Move::from_uci("e2e4").unwrap();
+        transpositions.set(1, 0, 3, TableScore::Exact(Score::new(10)), first, None);
+        assert!(transpositions.probe(1, 0).is_some());
+
+        // another thread has managed to write only the data word of its entry into the same slot
+        let slot = &transpositions.cluster(1).0[0];
+        let other = Entry { m: Move::from_uci("d2d4").unwrap(), depth: 7, ..slot.load().1 };
+        slot.data.store(other.pack(), Ordering::Relaxed);
+        assert!(transpositions.probe(1, 0).is_none());
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        // two threads keep storing more positions than a cluster can hold, overwriting each other's
+        // slots all the time; whatever is found for a position has to be what was stored for it
+        let transpositions = Arc::new(Transpositions::new(1));
+        let moves = ["e2e4", "d2d4", "g1f3", "c2c4", "b1c3", "f2f4", "g2g3", "b2b3"];
+        let positions: Vec<(u64, Move, Score)> = moves.iter().enumerate()
+            .map(|(idx, m)| ((idx as u64) << 32 | 1, Move::from_uci(m).unwrap(), Score::new(idx as i16 * 10)))
+            .collect();
+
+        let writers: Vec<_> = positions.chunks(4).map(|chunk| {
+            let transpositions = transpositions.clone();
+            let chunk = chunk.to_vec();
+            thread::spawn(move || {
+                for depth in 0..20000 {
+                    for &(hash, m, score) in &chunk {
+                        transpositions.set(hash, 0, (depth % 100) as i16, TableScore::Exact(score), m, None);
+                    }
+                }
+            })
+        }).collect();
+
+        for _ in 0..20000 {
+            for &(hash, m, score) in &positions {
+                if let Some(probe) = transpositions.probe(hash, 0) {
+                    assert_eq!(probe.m, m);
+                    assert!(matches!(probe.score, TableScore::Exact(found) if found == score));
                 }
             }
         }
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
     }
 }
@@ -1,5 +1,6 @@
 use std::sync::LazyLock;
-use rand::RngCore;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
 use crate::board::Board;
 use crate::types::{Bitboard, Side};
 
@@ -8,6 +9,8 @@ type PieceKeys = [SideKeys; 2];
 type EnPassantKeys = [u64; 8];
 type CastleKeys = [u64; 2];
 
+const ZOBRIST_SEED: u64 = 0x6b6f7079746f;
+
 pub struct Zobrist {
     keys_pawns: PieceKeys,
     keys_knights: PieceKeys,
@@ -24,50 +27,50 @@ pub struct Zobrist {
 mod init {
     use super::*;
 
-    fn generate_side_keys() -> SideKeys {
+    fn generate_side_keys(rng: &mut StdRng) -> SideKeys {
         let mut result = [0; 64];
-        let mut rng = rand::thread_rng();
         for i in 0..64 {
             result[i] = rng.next_u64();
         }
         result
     }
 
-    pub fn generate_piece_keys() -> PieceKeys {
-        [generate_side_keys(), generate_side_keys()]
+    pub fn generate_piece_keys(rng: &mut StdRng) -> PieceKeys {
+        [generate_side_keys(rng), generate_side_keys(rng)]
     }
 
-    pub fn generate_key() -> u64 {
-        rand::thread_rng().next_u64()
+    pub fn generate_key(rng: &mut StdRng) -> u64 {
+        rng.next_u64()
     }
 
-    pub fn generate_en_passant_keys() -> EnPassantKeys {
+    pub fn generate_en_passant_keys(rng: &mut StdRng) -> EnPassantKeys {
         let mut result = [0; 8];
-        let mut rng = rand::thread_rng();
         for i in 0..8 {
             result[i] = rng.next_u64();
         }
         result
     }
 
-    pub fn generate_castle_keys() -> CastleKeys {
-        [generate_key(), generate_key()]
+    pub fn generate_castle_keys(rng: &mut StdRng) -> CastleKeys {
+        [generate_key(rng), generate_key(rng)]
     }
 }
 
 impl Zobrist {
     fn new() -> Self {
+        // fixed seed, so that the hashes (and thus the searched trees) are the same on every run
+        let mut rng = StdRng::seed_from_u64(ZOBRIST_SEED);
         Self {
-            keys_pawns: init::generate_piece_keys(),
-            keys_knights: init::generate_piece_keys(),
-            keys_bishops: init::generate_piece_keys(),
-            keys_rooks: init::generate_piece_keys(),
-            keys_queens: init::generate_piece_keys(),
-            keys_kings: init::generate_piece_keys(),
-            key_black_to_move: init::generate_key(),
-            key_castle_kingside: init::generate_castle_keys(),
-            key_castle_queenside: init::generate_castle_keys(),
-            keys_en_passant: init::generate_en_passant_keys(),
+            keys_pawns: init::generate_piece_keys(&mut rng),
+            keys_knights: init::generate_piece_keys(&mut rng),
+            keys_bishops: init::generate_piece_keys(&mut rng),
+            keys_rooks: init::generate_piece_keys(&mut rng),
+            keys_queens: init::generate_piece_keys(&mut rng),
+            keys_kings: init::generate_piece_keys(&mut rng),
+            key_black_to_move: init::generate_key(&mut rng),
+            key_castle_kingside: init::generate_castle_keys(&mut rng),
+            key_castle_queenside: init::generate_castle_keys(&mut rng),
+            keys_en_passant: init::generate_en_passant_keys(&mut rng),
         }
     }
 
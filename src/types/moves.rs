@@ -48,6 +48,18 @@ impl From<u16> for Promotion {
     }
 }
 
+impl From<u16> for Move {
+    fn from(value: u16) -> Self {
+        Self { m: value }
+    }
+}
+
+impl From<Move> for u16 {
+    fn from(value: Move) -> Self {
+        value.m
+    }
+}
+
 impl From<Promotion> for Piece {
     fn from(value: Promotion) -> Self {
         match value {
@@ -69,17 +81,21 @@ impl Move {
         Self { m: 0 }
     }
 
-    pub fn from_str(from: &str, to: &str) -> Self {
-        Self::from_idx(from.into(), to.into())
+    pub fn from_str(from: &str, to: &str) -> Result<Self, String> {
+        Ok(Self::from_idx(Square::try_from(from)?, Square::try_from(to)?))
     }
 
-    pub fn from_str_prom(from: &str, to: &str, promotion: Promotion) -> Self {
-        let mut m = Self::from_str(from, to);
+    pub fn from_str_prom(from: &str, to: &str, promotion: Promotion) -> Result<Self, String> {
+        let mut m = Self::from_str(from, to)?;
         m.set_promotion(promotion);
-        m
+        Ok(m)
     }
 
-    pub fn from_uci(uci: &str) -> Self {
+    pub fn from_uci(uci: &str) -> Result<Self, String> {
+        if !uci.is_ascii() {
+            return Err(format!("invalid uci move: {}", uci));
+        }
+
         match uci.len() {
             4 => Self::from_str(&uci[0..2], &uci[2..4]),
             5 => Self::from_str_prom(
@@ -90,10 +106,10 @@ impl Move {
                     "r" => Promotion::Rook,
                     "b" => Promotion::Bishop,
                     "n" => Promotion::Knight,
-                    _ => panic!("invalid uci move: {} (bad promotion)", uci),
+                    _ => return Err(format!("invalid uci move: {} (bad promotion)", uci)),
                 },
             ),
-            _ => panic!("invalid uci move: {}", uci),
+            _ => Err(format!("invalid uci move: {}", uci)),
         }
     }
 
@@ -139,6 +155,10 @@ impl Move {
         Promotion::from((self.m & Self::MASK_PROMOTION) >> 12)
     }
 
+    pub fn has_promotion(&self) -> bool {
+        self.m & Self::MASK_HAS_PROMOTION != 0
+    }
+
     pub fn to_uci(&self) -> String {
         match self.m & 0b100000000000000 != 0 {
             false => format!("{}{}", self.get_from().to_string(), self.get_to().to_string()),
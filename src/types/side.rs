@@ -1,7 +1,7 @@
 use std::fmt::{Display, Formatter};
 use std::ops::{Index, IndexMut, Not};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 #[repr(usize)]
 pub enum Side {
     White = 0,
@@ -89,38 +89,43 @@ impl From<usize> for Square {
     }
 }
 
-impl From<&str> for Square {
-    fn from(value: &str) -> Self {
-        fn get_file(pos: &mut Chars) -> usize {
+impl TryFrom<&str> for Square {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        fn get_file(pos: &mut Chars) -> Option<usize> {
             match pos.next() {
-                Some('a') => 0,
-                Some('b') => 1,
-                Some('c') => 2,
-                Some('d') => 3,
-                Some('e') => 4,
-                Some('f') => 5,
-                Some('g') => 6,
-                Some('h') => 7,
-                _ => panic!("Invalid file"),
+                Some('a') => Some(0),
+                Some('b') => Some(1),
+                Some('c') => Some(2),
+                Some('d') => Some(3),
+                Some('e') => Some(4),
+                Some('f') => Some(5),
+                Some('g') => Some(6),
+                Some('h') => Some(7),
+                _ => None,
             }
         }
-        fn get_rank(pos: &mut Chars) -> usize {
+        fn get_rank(pos: &mut Chars) -> Option<usize> {
             match pos.next() {
-                Some('1') => 0,
-                Some('2') => 1,
-                Some('3') => 2,
-                Some('4') => 3,
-                Some('5') => 4,
-                Some('6') => 5,
-                Some('7') => 6,
-                Some('8') => 7,
-                _ => panic!("Invalid rank"),
+                Some('1') => Some(0),
+                Some('2') => Some(1),
+                Some('3') => Some(2),
+                Some('4') => Some(3),
+                Some('5') => Some(4),
+                Some('6') => Some(5),
+                Some('7') => Some(6),
+                Some('8') => Some(7),
+                _ => None,
             }
         }
         let mut pos = value.chars();
-        let file = get_file(&mut pos);
-        let rank = get_rank(&mut pos);
-        Square::from_coords(file, rank)
+        let file = get_file(&mut pos).ok_or(format!("invalid file in square: {}", value))?;
+        let rank = get_rank(&mut pos).ok_or(format!("invalid rank in square: {}", value))?;
+        match pos.next() {
+            None => Ok(Square::from_coords(file, rank)),
+            Some(_) => Err(format!("invalid square: {}", value)),
+        }
     }
 }
 
@@ -1,5 +1,37 @@
+use crate::search::Output;
+
 mod uci;
+mod xboard;
+
+fn read_command() -> String {
+    let mut buffer = String::new();
+    let read = std::io::stdin()
+        .read_line(&mut buffer)
+        .expect("Reading from stdin failed");
+    let line = match read {
+        0 => String::from("quit"),
+        _ => String::from(buffer.trim()),
+    };
+    crate::console::log_input(line.as_str());
+    line
+}
+
+// errors are reported in the form expected by the protocol in use
+fn report(output: Output, result: Result<(), String>) {
+    if let Err(e) = result {
+        match output {
+            Output::Uci => output!("info string error: {}", e),
+            Output::Xboard => output!("Error ({})", e),
+            Output::Silent => (),
+        }
+    }
+}
 
 pub fn start() {
-    uci::UCI::new().run();
+    // the protocol is chosen by the first command the GUI sends
+    let first_command = read_command();
+    match first_command.as_str() {
+        "xboard" => xboard::XBoard::new().run(),
+        _ => uci::UCI::new().run(first_command.as_str()),
+    }
 }
@@ -1,17 +1,58 @@
+use std::fmt::Display;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
 use scanner_rust::ScannerAscii;
+use crate::console;
+use crate::crash;
 use crate::board::{Board, FenConsumer, FenProducer};
 use crate::types::Move;
+use crate::moves_generation;
 use crate::moves_generation::perft;
 use crate::search;
-use crate::search::{Searcher, Verbosity};
+use crate::search::{Output, Searcher, Signals, Verbosity, SEARCH_STACK_SIZE};
 use crate::transpositions::Transpositions;
+use super::report;
+
+fn parse_value<T: FromStr>(name: &str, value: &str) -> Result<T, String> where T::Err: Display {
+    value.parse::<T>().map_err(|e| format!("invalid value for {}: '{}' ({})", name, value, e))
+}
+
+fn next_value<T: FromStr>(scanner: &mut ScannerAscii<&[u8]>, name: &str) -> Result<T, String> where T::Err: Display {
+    match scanner.next() {
+        Err(e) => Err(format!("cannot read {}: {}", name, e)),
+        Ok(None) => Err(format!("missing value for {}", name)),
+        Ok(Some(value)) => parse_value(name, value.as_str()),
+    }
+}
+
+pub fn apply_moves(board: &mut Board, moves: &str) -> Result<(), String> {
+    for token in moves.split_whitespace().skip_while(|token| *token == "moves") {
+        let m = Move::from_uci(token)?;
+        if !moves_generation::generate_all(board).contains(&m) {
+            return Err(format!("illegal move: {}", token));
+        }
+        board.make_move(m);
+    }
+    Ok(())
+}
 
 pub struct UCI {
     board: Board,
     last_position: String,
     book: bool,
-    transpositions: Transpositions,
+    multi_pv: usize,
+    threads: usize,
+    limit_strength: bool,
+    elo: i32,
+    skill_level: f64,
+    show_wdl: bool,
+    move_overhead: i32,
+    transpositions: Arc<Transpositions>,
+    signals: Arc<Signals>,
+    search: Option<JoinHandle<()>>,
 }
 
 impl UCI {
@@ -20,171 +61,274 @@ impl UCI {
             board: Board::from_starting_position(),
             last_position: String::new(),
             book: false,
-            transpositions: Transpositions::new(64),
+            multi_pv: 1,
+            threads: 1,
+            limit_strength: false,
+            elo: search::MAX_ELO,
+            skill_level: search::MAX_SKILL_LEVEL,
+            show_wdl: false,
+            move_overhead: search::DEFAULT_MOVE_OVERHEAD,
+            transpositions: Arc::new(Transpositions::new(64)),
+            signals: Arc::new(Signals::new()),
+            search: None,
         }
     }
 
-    pub fn run(&mut self) {
-        loop {
-            let mut buffer = String::new();
-            std::io::stdin()
-                .read_line(&mut buffer)
-                .expect("Reading from stdin failed");
-            let line = buffer.trim();
-            match line {
-                "quit" => break,
-                "stop" => (),
-                "uci" => self.uci(),
-                "eval" => self.eval(),
-                "isready" => self.isready(),
-                "ucinewgame" => self.ucinewgame(),
-                "currentfen" => println!("{}", self.board.export_fen()),
-                cmd if cmd.starts_with("position") => self.position(cmd.strip_prefix("position ")),
-                cmd if cmd.starts_with("go") => self.go(cmd.strip_prefix("go").unwrap_or("").trim()),
-                cmd if cmd.starts_with("setoption") => self.setoption(cmd.strip_prefix("setoption").unwrap_or("").trim()),
-                _ => println!("info string unknown command"),
+    pub fn run(&mut self, first_command: &str) {
+        let mut line = String::from(first_command);
+        while self.execute(line.as_str()) {
+            line = super::read_command();
+        }
+    }
+
+    fn execute(&mut self, line: &str) -> bool {
+        match line {
+            "quit" => {
+                self.stop();
+                return false;
+            }
+            "stop" => self.stop(),
+            "ponderhit" => self.signals.ponderhit(),
+            "uci" => self.uci(),
+            "eval" => self.eval(),
+            "isready" => self.isready(),
+            "ucinewgame" => self.ucinewgame(),
+            "currentfen" => output!("{}", self.board.export_fen()),
+            cmd if cmd.starts_with("bench") => report(Output::Uci, self.bench(cmd.strip_prefix("bench").unwrap_or("").trim())),
+            cmd if cmd.starts_with("position") => report(Output::Uci, self.position(cmd.strip_prefix("position "))),
+            cmd if cmd.starts_with("go") => report(Output::Uci, self.go(cmd.strip_prefix("go").unwrap_or("").trim())),
+            cmd if cmd.starts_with("setoption") => report(Output::Uci, self.setoption(cmd.strip_prefix("setoption").unwrap_or("").trim())),
+            _ => output!("info string unknown command"),
+        }
+        true
+    }
+
+    fn stop(&mut self) {
+        self.signals.stop();
+        if let Some(search) = self.search.take() {
+            // the panic has already been reported by the hook, the engine keeps running without the search
+            if search.join().is_err() {
+                output!("info string search thread panicked");
             }
         }
     }
 
     fn uci(&self) {
-        println!("id name kopyto");
-        println!("id author szajnapawel@gmail.com");
-        println!("option name Book type check default false");
-        println!("option name Hash type spin default 64 min 1 max 2048");
-        println!("uciok");
+        output!("id name kopyto");
+        output!("id author szajnapawel@gmail.com");
+        output!("option name Book type check default false");
+        output!("option name Ponder type check default false");
+        output!("option name Hash type spin default 64 min 1 max 2048");
+        output!("option name MultiPV type spin default 1 min 1 max 256");
+        output!("option name Threads type spin default 1 min 1 max 256");
+        output!("option name UCI_LimitStrength type check default false");
+        output!("option name UCI_Elo type spin default {} min {} max {}", search::MAX_ELO, search::MIN_ELO, search::MAX_ELO);
+        output!("option name UCI_ShowWDL type check default false");
+        output!("option name Move Overhead type spin default {} min 0 max {}", search::DEFAULT_MOVE_OVERHEAD, search::MAX_MOVE_OVERHEAD);
+        output!("option name Debug Log File type string default <empty>");
+        output!("option name Crash File type string default <empty>");
+        output!("option name Skill Level type spin default {} min 0 max {}", search::MAX_SKILL_LEVEL, search::MAX_SKILL_LEVEL);
+        #[cfg(feature = "tune")]
+        for parameter in search::PARAMETERS {
+            output!("option name {} type spin default {} min {} max {}", parameter.name, parameter.default, parameter.min, parameter.max);
+        }
+        output!("uciok");
     }
 
     fn isready(&self) {
-        println!("readyok");
+        output!("readyok");
     }
 
     fn ucinewgame(&mut self) {
+        self.stop();
         self.board = Board::new();
     }
 
-    fn setoption(&mut self, option: &str) {
+    fn setoption(&mut self, option: &str) -> Result<(), String> {
+        self.stop();
         let mut scanner = ScannerAscii::new(option.as_bytes());
         let mut option_name = String::new();
         let mut option_value = String::new();
+        let mut reading_value = false;
 
+        // both the names and the values may consist of several words
         loop {
             match scanner.next() {
-                Err(e) => println!("info string cannot parse option: {}", e),
-                Ok(mode) => match mode {
+                Err(e) => return Err(format!("cannot parse option: {}", e)),
+                Ok(token) => match token {
                     None => break,
-                    Some(mode) => match mode.as_str() {
-                        "name" => option_name = scanner.next().unwrap().unwrap(),
-                        "value" => option_value = scanner.next().unwrap().unwrap(),
-                        _ => (),
+                    Some(token) => match token.as_str() {
+                        "name" if !reading_value && option_name.is_empty() => (),
+                        "value" if !reading_value => reading_value = true,
+                        _ => {
+                            let target = if reading_value { &mut option_value } else { &mut option_name };
+                            if !target.is_empty() {
+                                target.push(' ');
+                            }
+                            target.push_str(token.as_str());
+                        }
                     }
                 }
             }
         }
 
+        let value = option_value.as_str();
         match option_name.as_str() {
-            "Book" => self.book = bool::from_str(option_value.as_str()).unwrap(),
-            "Hash" => self.transpositions = Transpositions::new(usize::from_str(option_value.as_str()).unwrap()),
-            _ => println!("unknown option: {}, ignoring", option_name),
-        }
-    }
-
-    fn position_moves(&mut self, moves: Option<&str>) {
-        match moves {
-            None => {}
-            Some(str) if str.trim().is_empty() => {}
-            Some(str) if str.starts_with(" ") => self.position_moves(Some(str.trim())),
-            Some(str) if str.trim().starts_with("moves ") => self.position_moves(str.trim().strip_prefix("moves ")),
-            Some(moves) => {
-                let space = moves.find(" ");
-                let first_move = if space.is_some() {
-                    &moves[..space.unwrap()]
-                } else {
-                    moves
-                };
-                self.board.make_move(Move::from_uci(first_move));
-                let tail = moves[first_move.len()..].trim();
-                self.position_moves(Some(tail));
+            "Ponder" => (),
+            "Book" => self.book = parse_value(&option_name, value)?,
+            "MultiPV" => self.multi_pv = parse_value(&option_name, value)?,
+            "UCI_LimitStrength" => self.limit_strength = parse_value(&option_name, value)?,
+            "UCI_Elo" => self.elo = parse_value(&option_name, value)?,
+            "Skill Level" => self.skill_level = parse_value(&option_name, value)?,
+            "UCI_ShowWDL" => self.show_wdl = parse_value(&option_name, value)?,
+            "Threads" => self.threads = parse_value(&option_name, value)?,
+            "Move Overhead" => self.move_overhead = parse_value::<i32>(&option_name, value)?.clamp(0, search::MAX_MOVE_OVERHEAD),
+            "Debug Log File" => console::set_log_file(value)?,
+            "Crash File" => crash::set_crash_file(value),
+            "Hash" => self.transpositions = Arc::new(Transpositions::new(parse_value(&option_name, value)?)),
+            #[cfg(feature = "tune")]
+            name if search::find_parameter(name).is_some() => {
+                search::find_parameter(name).unwrap().set(parse_value(&option_name, value)?)?
             }
+            _ => output!("unknown option: {}, ignoring", option_name),
         }
+        Ok(())
     }
 
-    fn position(&mut self, cmd: Option<&str>) {
+    fn position(&mut self, cmd: Option<&str>) -> Result<(), String> {
+        self.stop();
         match cmd {
-            None => println!("info string invalid position request"),
+            None => Err(String::from("invalid position request")),
             Some(cmd) => match cmd {
                 cmd if cmd.starts_with("startpos") => {
+                    let mut board = self.board.clone();
                     if !self.last_position.is_empty() && cmd.starts_with(self.last_position.as_str()) {
-                        let remainder = cmd.strip_prefix(self.last_position.as_str());
-                        self.position_moves(remainder);
+                        apply_moves(&mut board, &cmd[self.last_position.len()..])?;
                     } else {
-                        self.board = Board::from_starting_position();
-                        if cmd.starts_with("startpos moves ") {
-                            self.position_moves(cmd.strip_prefix("startpos moves "));
-                        }
+                        board = Board::from_starting_position();
+                        apply_moves(&mut board, &cmd["startpos".len()..])?;
                     }
+                    crash::set_position(&format!("position {}", cmd), &board);
+                    self.board = board;
                     self.last_position = String::from(cmd);
+                    Ok(())
                 }
                 cmd if cmd.starts_with("fen ") => {
-                    let moves = cmd.find(" moves ");
-                    let fen = if moves.is_some() {
-                        &cmd[4..moves.unwrap()]
-                    } else {
-                        &cmd[4..]
-                    };
-                    self.board = Board::from_fen(fen);
-                    if moves.is_some() {
-                        let moves_str = &cmd[(moves.unwrap() + 7)..];
-                        self.position_moves(Some(moves_str));
-                    }
+                    let (fen, moves) = cmd[4..].split_once(" moves ").unwrap_or((&cmd[4..], ""));
+                    let mut board = Board::from_fen(fen.trim())?;
+                    apply_moves(&mut board, moves)?;
+                    crash::set_position(&format!("position {}", cmd), &board);
+                    self.board = board;
+                    self.last_position.clear();
+                    Ok(())
                 }
-                _ => println!("info string unknown position format"),
+                _ => Err(String::from("unknown position format")),
             },
         }
     }
 
-    fn parse_go_options(&self, options: &mut search::Options, cmd: &str) {
+    fn parse_go_options(&self, options: &mut search::Options, cmd: &str) -> Result<(), String> {
         let mut scanner = ScannerAscii::new(cmd.as_bytes());
+        let mut search_moves = false;
         loop {
             match scanner.next() {
-                Err(e) => println!("info string parsing failed: {}", e),
+                Err(e) => return Err(format!("parsing failed: {}", e)),
                 Ok(result) => match result {
                     None => break,
                     Some(command) => match command.as_str() {
-                        "infinite" => options.depth = None,
-                        "depth" => options.depth = scanner.next_i16().unwrap(),
-                        "wtime" => options.white_time = scanner.next_i32().unwrap().unwrap(),
-                        "btime" => options.black_time = scanner.next_i32().unwrap().unwrap(),
-                        "winc" => options.white_increment = scanner.next_i32().unwrap().unwrap(),
-                        "binc" => options.black_increment = scanner.next_i32().unwrap().unwrap(),
-                        "movetime" => options.target_time = scanner.next_i32().unwrap(),
+                        "searchmoves" => {
+                            search_moves = true;
+                            continue;
+                        }
+                        m if search_moves && m.len() >= 4 && m.as_bytes()[1].is_ascii_digit() => {
+                            options.search_moves.push(Move::from_uci(m)?);
+                            continue;
+                        }
+                        "nodes" => options.nodes = Some(next_value(&mut scanner, "nodes")?),
+                        "mate" => options.mate = Some(next_value(&mut scanner, "mate")?),
+                        "movestogo" => options.moves_to_go = Some(next_value(&mut scanner, "movestogo")?),
+                        "ponder" => options.ponder = true,
+                        "infinite" => {
+                            options.depth = None;
+                            options.infinite = true;
+                        }
+                        "depth" => options.depth = Some(next_value(&mut scanner, "depth")?),
+                        "wtime" => options.white_time = next_value(&mut scanner, "wtime")?,
+                        "btime" => options.black_time = next_value(&mut scanner, "btime")?,
+                        "winc" => options.white_increment = next_value(&mut scanner, "winc")?,
+                        "binc" => options.black_increment = next_value(&mut scanner, "binc")?,
+                        "movetime" => options.target_time = Some(next_value(&mut scanner, "movetime")?),
                         _ => (),
                     }
                 },
             }
+            search_moves = false;
         }
+        Ok(())
     }
 
-    fn go(&mut self, cmd: &str) {
-        if cmd.starts_with("perft") {
-            let cmd = cmd.strip_prefix("perft ");
-            let depth = cmd.unwrap().to_string().parse::<usize>();
-            match depth {
-                Err(what) => panic!("cannot parse: {}", what.to_string()),
-                Ok(depth) => { perft(&mut self.board, depth); },
-            }
-            return;
+    fn go(&mut self, cmd: &str) -> Result<(), String> {
+        self.stop();
+        crash::set_go(&format!("go {}", cmd));
+
+        if let Some(depth) = cmd.strip_prefix("perft") {
+            perft(&mut self.board, parse_value("perft depth", depth.trim())?);
+            return Ok(());
         }
 
         let mut options = search::Options::new();
-        self.parse_go_options(&mut options, cmd);
-        let mut searcher = Searcher::new(self.board.clone(), &mut self.transpositions, self.book);
-        let result = searcher.go(options);
+        options.multi_pv = self.multi_pv;
+        options.threads = self.threads;
+        options.show_wdl = self.show_wdl;
+        options.move_overhead = self.move_overhead;
+        options.skill_level = match self.limit_strength {
+            true => search::level_from_elo(self.elo),
+            false => self.skill_level,
+        };
+        self.parse_go_options(&mut options, cmd)?;
+
+        let board = self.board.clone();
+        let book = self.book;
+        let transpositions = self.transpositions.clone();
+        let signals = self.signals.clone();
+        signals.reset(options.ponder);
+
+        let search = thread::Builder::new()
+            .name(String::from("search"))
+            .stack_size(SEARCH_STACK_SIZE)
+            .spawn(move || {
+                let infinite = options.infinite;
+                let mut searcher = Searcher::new(board, &transpositions, book, signals.clone());
+                let result = searcher.go(options);
+
+                // in infinite and ponder modes the GUI expects the best move only after it has sent stop
+                // (or ponderhit, in the latter case)
+                while (infinite || signals.pondering()) && !signals.stopped() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+
+                match result.ponder {
+                    Some(ponder) => output!("bestmove {} ponder {}", result.m.to_uci(), ponder.to_uci()),
+                    None => output!("bestmove {}", result.m.to_uci()),
+                }
+            })
+            .expect("Cannot spawn search thread");
+
+        self.search = Some(search);
+        Ok(())
+    }
 
-        println!("bestmove {}", result.to_uci());
+    fn bench(&mut self, cmd: &str) -> Result<(), String> {
+        self.stop();
+        let depth = match cmd {
+            "" => search::BENCH_DEPTH,
+            depth => parse_value("bench depth", depth)?,
+        };
+        search::bench(depth);
+        Ok(())
     }
 
     fn eval(&self) {
-        println!("{}", search::evaluate(&self.board, Verbosity::Verbose));
+        output!("{}", search::evaluate(&self.board, Verbosity::Verbose));
     }
 }
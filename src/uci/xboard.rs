@@ -0,0 +1,263 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::thread::JoinHandle;
+use crate::board::{Board, FenConsumer};
+use crate::crash;
+use crate::moves_generation;
+use crate::search;
+use crate::search::{Output, Searcher, Signals, SEARCH_STACK_SIZE};
+use crate::transpositions::Transpositions;
+use crate::types::Side;
+use super::report;
+use super::uci::apply_moves;
+
+// time per move used until the GUI sends a time control
+const DEFAULT_MOVE_TIME: i32 = 5000;
+
+fn game_result(board: &mut Board) -> Option<&'static str> {
+    if !moves_generation::generate_all(board).is_empty() {
+        return None;
+    }
+    Some(match (board.in_check(), board.side_to_move()) {
+        (false, _) => "1/2-1/2 {Stalemate}",
+        (true, Side::White) => "0-1 {Black mates}",
+        (true, Side::Black) => "1-0 {White mates}",
+    })
+}
+
+fn parse_seconds(value: &str) -> Result<i32, String> {
+    value.parse::<f64>()
+        .map(|seconds| (seconds * 1000.0) as i32)
+        .map_err(|_| format!("invalid time: {}", value))
+}
+
+// base time in the level command is given either as minutes or as minutes:seconds
+fn parse_minutes(value: &str) -> Result<i32, String> {
+    let (minutes, seconds) = value.split_once(':').unwrap_or((value, "0"));
+    match (minutes.parse::<i32>(), seconds.parse::<i32>()) {
+        (Ok(minutes), Ok(seconds)) => Ok((minutes * 60 + seconds) * 1000),
+        _ => Err(format!("invalid base time: {}", value)),
+    }
+}
+
+fn parse_centiseconds(value: &str) -> Result<i32, String> {
+    value.parse::<i32>()
+        .map(|centiseconds| centiseconds * 10)
+        .map_err(|_| format!("invalid time: {}", value))
+}
+
+pub struct XBoard {
+    board: Board,
+    force: bool,
+    engine_side: Side,
+    post: bool,
+    depth: Option<i16>,
+    move_time: Option<i32>,
+    moves_per_session: i32,
+    increment: i32,
+    time: i32,
+    opponent_time: i32,
+    transpositions: Arc<Transpositions>,
+    signals: Arc<Signals>,
+    discard: Arc<AtomicBool>,
+    search: Option<JoinHandle<Option<Board>>>,
+}
+
+impl XBoard {
+    pub fn new() -> Self {
+        Self {
+            board: Board::from_starting_position(),
+            force: false,
+            engine_side: Side::Black,
+            post: false,
+            depth: None,
+            move_time: None,
+            moves_per_session: 0,
+            increment: 0,
+            time: i32::MAX,
+            opponent_time: i32::MAX,
+            transpositions: Arc::new(Transpositions::new(64)),
+            signals: Arc::new(Signals::new()),
+            discard: Arc::new(AtomicBool::new(false)),
+            search: None,
+        }
+    }
+
+    pub fn run(&mut self) {
+        loop {
+            let line = super::read_command();
+            let (command, args) = line.split_once(' ').unwrap_or((line.as_str(), ""));
+            match command {
+                "quit" => {
+                    self.abort();
+                    break;
+                }
+                "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer" | "result" => (),
+                "protover" => self.features(),
+                "ping" => output!("pong {}", args),
+                "new" => self.new_game(),
+                "force" => {
+                    self.abort();
+                    self.force = true;
+                }
+                "go" => {
+                    self.abort();
+                    self.force = false;
+                    self.engine_side = self.board.side_to_move();
+                    self.think();
+                }
+                "?" => self.finish(),
+                "post" => self.post = true,
+                "nopost" => self.post = false,
+                "usermove" => report(Output::Xboard, self.usermove(args)),
+                "level" => report(Output::Xboard, self.level(args)),
+                "st" => report(Output::Xboard, parse_seconds(args).map(|time| self.move_time = Some(time))),
+                "sd" => report(Output::Xboard, args.parse::<i16>()
+                    .map(|depth| self.depth = Some(depth))
+                    .map_err(|_| format!("invalid depth: {}", args))),
+                "time" => report(Output::Xboard, parse_centiseconds(args).map(|time| self.time = time)),
+                "otim" => report(Output::Xboard, parse_centiseconds(args).map(|time| self.opponent_time = time)),
+                "undo" => self.undo(1),
+                "remove" => self.undo(2),
+                "setboard" => report(Output::Xboard, self.setboard(args)),
+                _ => output!("Error (unknown command): {}", line),
+            }
+        }
+    }
+
+    fn features(&self) {
+        output!("feature myname=\"kopyto\" setboard=1 usermove=1 ping=1 sigint=0 sigterm=0 colors=0 analyze=0 done=1");
+    }
+
+    // waits for the search to end and takes over the position after the move it has announced, if any
+    fn finish(&mut self) {
+        self.signals.stop();
+        if let Some(search) = self.search.take() {
+            match search.join() {
+                Ok(Some(board)) => self.board = board,
+                Ok(None) => (),
+                // the panic has already been reported by the hook, the engine keeps running without the search
+                Err(_) => output!("Error (search thread panicked)"),
+            }
+        }
+    }
+
+    // stops the search without announcing its move
+    fn abort(&mut self) {
+        self.discard.store(true, Ordering::Relaxed);
+        self.finish();
+    }
+
+    fn new_game(&mut self) {
+        self.abort();
+        self.board = Board::from_starting_position();
+        self.force = false;
+        self.engine_side = Side::Black;
+        self.depth = None;
+        self.move_time = None;
+        self.transpositions = Arc::new(Transpositions::new(64));
+    }
+
+    fn setboard(&mut self, fen: &str) -> Result<(), String> {
+        self.abort();
+        self.board = Board::from_fen(fen)?;
+        crash::set_position(&format!("setboard {}", fen), &self.board);
+        Ok(())
+    }
+
+    fn undo(&mut self, count: usize) {
+        self.abort();
+        for _ in 0..count.min(self.board.moves_played()) {
+            self.board.unmake_move();
+        }
+    }
+
+    fn usermove(&mut self, m: &str) -> Result<(), String> {
+        self.finish();
+        let mut board = self.board.clone();
+        if apply_moves(&mut board, m).is_err() {
+            output!("Illegal move: {}", m);
+            return Ok(());
+        }
+        self.board = board;
+        crash::set_position(&format!("usermove {}", m), &self.board);
+        if !self.force && self.board.side_to_move() == self.engine_side {
+            self.think();
+        }
+        Ok(())
+    }
+
+    fn level(&mut self, args: &str) -> Result<(), String> {
+        let args: Vec<&str> = args.split_whitespace().collect();
+        if args.len() != 3 {
+            return Err(String::from("level needs three arguments"));
+        }
+        self.moves_per_session = args[0].parse::<i32>().map_err(|_| format!("invalid moves count: {}", args[0]))?;
+        self.time = parse_minutes(args[1])?;
+        self.opponent_time = self.time;
+        self.increment = parse_seconds(args[2])?;
+        self.move_time = None;
+        Ok(())
+    }
+
+    fn think(&mut self) {
+        if let Some(result) = game_result(&mut self.board) {
+            output!("{}", result);
+            return;
+        }
+
+        let side = self.board.side_to_move();
+        let mut options = search::Options::new();
+        options.depth = self.depth;
+        options.target_time = self.move_time;
+        options.white_time = side.choose(self.time, self.opponent_time);
+        options.black_time = side.choose(self.opponent_time, self.time);
+        options.white_increment = self.increment;
+        options.black_increment = self.increment;
+        options.output = if self.post { Output::Xboard } else { Output::Silent };
+        if self.moves_per_session > 0 {
+            let moves_made = (self.board.full_moves_count as i32 - 1) % self.moves_per_session;
+            options.moves_to_go = Some(self.moves_per_session - moves_made);
+        }
+        if self.move_time.is_none() && self.time == i32::MAX {
+            // without any time control the search is limited by the depth, if there is one
+            match self.depth {
+                Some(_) => options.infinite = true,
+                None => options.target_time = Some(DEFAULT_MOVE_TIME),
+            }
+        }
+
+        crash::set_go(&format!(
+            "go depth {:?} movetime {:?} time {} otim {} inc {} moves to go {:?}",
+            options.depth, options.target_time, self.time, self.opponent_time, self.increment, options.moves_to_go));
+
+        let mut board = self.board.clone();
+        let transpositions = self.transpositions.clone();
+        let signals = self.signals.clone();
+        let discard = self.discard.clone();
+        signals.reset(false);
+        discard.store(false, Ordering::Relaxed);
+
+        let search = thread::Builder::new()
+            .name(String::from("search"))
+            .stack_size(SEARCH_STACK_SIZE)
+            .spawn(move || {
+                let result = Searcher::new(board.clone(), &transpositions, false, signals).go(options);
+                match discard.load(Ordering::Relaxed) {
+                    true => None,
+                    false => {
+                        output!("move {}", result.m.to_uci());
+                        board.make_move(result.m);
+                        if let Some(result) = game_result(&mut board) {
+                            output!("{}", result);
+                        }
+                        Some(board)
+                    }
+                }
+            })
+            .expect("Cannot spawn search thread");
+
+        self.search = Some(search);
+    }
+}
@@ -0,0 +1,153 @@
+"""Plays kopyto against itself to collect positions for fitting the WDL model.
+
+    python3 tools/selfplay.py <engine> <games> <seed> <output>
+    target/release/kopyto fitwdl <output>
+
+Every game starts from a few random plies, chosen among the moves scoring within a pawn of the
+best one at depth 1, and goes on with a fixed number of nodes per move. Each searched position
+is written as "fen;score;result", with the score from the white point of view. Games are
+adjudicated once the score stays decisive or close to zero for long enough. The searches are
+limited by nodes only, so the same seed and build give the same file.
+"""
+
+import random
+import subprocess
+import sys
+
+NODES = 10000
+OPENING_PLIES = 8
+OPENING_MARGIN = 100
+MAX_PLIES = 400
+WIN_SCORE = 1000
+WIN_PLIES = 8
+DRAW_SCORE = 10
+DRAW_PLIES = 16
+DRAW_MIN_PLIES = 80
+
+
+class Engine:
+    def __init__(self, path):
+        self.process = subprocess.Popen([path], stdin=subprocess.PIPE, stdout=subprocess.PIPE, text=True, bufsize=1)
+        self.send("uci")
+        self.wait("uciok")
+
+    def send(self, command):
+        self.process.stdin.write(command + "\n")
+        self.process.stdin.flush()
+
+    def wait(self, prefix):
+        lines = []
+        while True:
+            line = self.process.stdout.readline()
+            if not line:
+                raise RuntimeError("engine died")
+            lines.append(line.strip())
+            if lines[-1].startswith(prefix):
+                return lines
+
+    def fen(self, moves):
+        self.send("position startpos moves " + " ".join(moves))
+        self.send("currentfen")
+        self.send("isready")
+        return self.wait("readyok")[-2]
+
+    def search(self, moves, go):
+        self.send("position startpos moves " + " ".join(moves))
+        self.send(go)
+        return self.wait("bestmove")
+
+
+def parse_score(line):
+    tokens = line.split()
+    idx = tokens.index("score")
+    bound = tokens[idx + 3] in ("lowerbound", "upperbound")
+    return tokens[idx + 1], int(tokens[idx + 2]), bound
+
+
+def root_moves(engine, moves):
+    engine.send("setoption name MultiPV value 256")
+    lines = engine.search(moves, "go depth 1")
+    engine.send("setoption name MultiPV value 1")
+    found = {}
+    for line in lines:
+        if line.startswith("info") and " pv " in line and " score " in line:
+            kind, value, _ = parse_score(line)
+            found[line.split(" pv ")[1].split()[0]] = value if kind == "cp" else (10000 if value > 0 else -10000)
+    return found
+
+
+def play(engine, rng):
+    moves = []
+    engine.send("ucinewgame")
+    for _ in range(OPENING_PLIES):
+        candidates = root_moves(engine, moves)
+        if not candidates:
+            return None, []
+        best = max(candidates.values())
+        moves.append(rng.choice(sorted(m for m, value in candidates.items() if value >= best - OPENING_MARGIN)))
+
+    records = []
+    seen = {}
+    winning = 0
+    winning_side = None
+    quiet = 0
+    while True:
+        fen = engine.fen(moves)
+        fields = fen.split()
+        white = fields[1] == "w"
+        key = " ".join(fields[:4])
+        seen[key] = seen.get(key, 0) + 1
+        if seen[key] >= 3 or int(fields[4]) >= 100 or len(moves) >= MAX_PLIES:
+            return "1/2-1/2", records
+
+        lines = engine.search(moves, "go nodes %d" % NODES)
+        best_move = lines[-1].split()[1]
+        infos = [line for line in lines if line.startswith("info") and " score " in line]
+        if best_move == "a1a1" or not infos:
+            # no legal moves, and no mate has been announced before: stalemate
+            return "1/2-1/2", records
+
+        kind, value, bound = parse_score(infos[-1])
+        if kind == "mate":
+            return ("1-0" if (value > 0) == white else "0-1"), records
+
+        score = value if white else -value
+        if not bound:
+            records.append((fen, score))
+
+        if abs(score) >= WIN_SCORE:
+            winning = winning + 1 if winning_side == (score > 0) else 1
+            winning_side = score > 0
+        else:
+            winning = 0
+        if winning >= WIN_PLIES:
+            return ("1-0" if score > 0 else "0-1"), records
+
+        quiet = quiet + 1 if abs(score) <= DRAW_SCORE else 0
+        if len(moves) >= DRAW_MIN_PLIES and quiet >= DRAW_PLIES:
+            return "1/2-1/2", records
+
+        moves.append(best_move)
+
+
+def main():
+    if len(sys.argv) != 5:
+        sys.exit(__doc__)
+    engine, games, seed, path = Engine(sys.argv[1]), int(sys.argv[2]), int(sys.argv[3]), sys.argv[4]
+    rng = random.Random(seed)
+    results = {}
+    positions = 0
+    with open(path, "w") as output:
+        for game in range(games):
+            result, records = play(engine, rng)
+            if result is None:
+                continue
+            results[result] = results.get(result, 0) + 1
+            for fen, score in records:
+                output.write("%s;%d;%s\n" % (fen, score, result))
+            positions += len(records)
+    print("games: %d positions: %d results: %s" % (games, positions, results))
+
+
+if __name__ == "__main__":
+    main()